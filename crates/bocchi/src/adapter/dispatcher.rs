@@ -0,0 +1,389 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    task::JoinSet,
+    time,
+};
+
+use crate::{
+    adapter::Caller,
+    bot::BotConfig,
+    chain::{Context, MatchUnion, QuickOperationSender, Sessions},
+    media::MediaStore,
+    middleware::Next,
+    plugin::{Hook, Plugin},
+    schema::{Event, GetMsgParams, QuickOperation},
+    switch::Scope,
+};
+
+/// 停止时等待正在执行的处理器与定时任务的最长时间，超时后直接取消，停止钩子同样使用该时限
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 负责将收到的事件分发给插件，不同的 Adapter 共用同一套分发逻辑
+#[derive(Clone)]
+pub(crate) struct Dispatcher {
+    plugins: Arc<Vec<Plugin>>,
+    /// 每个 MatchUnion 与其所属插件的名称
+    match_unions: Arc<Vec<(Cow<'static, str>, Arc<MatchUnion>)>>,
+    /// 有插件使用 `Rule::to_me` 时才需要查询回复的消息是否由 bot 发送
+    resolve_reply: bool,
+    media: Arc<MediaStore>,
+    config: Arc<BotConfig>,
+    sessions: Arc<Sessions>,
+    /// 正在运行的定时任务
+    jobs: Arc<Mutex<JoinSet<()>>>,
+    /// 通知定时任务不再等待下一次触发
+    stop_jobs: Arc<watch::Sender<bool>>,
+    /// 正在处理事件的任务
+    tasks: Arc<Mutex<JoinSet<()>>>,
+    /// 上一个事件交给会话后关闭的通道，用于让会话按照收到事件的顺序接收消息
+    fed: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+    /// 开始停止后不再处理新的事件
+    closing: Arc<AtomicBool>,
+    /// 停止流程全部完成，此时 Adapter 可以关闭连接
+    closed: Arc<watch::Sender<bool>>,
+    /// 连接建立与断开的钩子交给同一个任务按顺序执行，避免快速重连时断开钩子晚于连接钩子执行
+    lifecycle: mpsc::UnboundedSender<LifecycleHooks>,
+}
+
+/// 需要执行的一组钩子，依次为钩子名称、从插件中取出钩子的方法与调用钩子使用的 Caller
+type LifecycleHooks = (&'static str, fn(&Plugin) -> &[Hook], Arc<dyn Caller>);
+
+impl Dispatcher {
+    pub(crate) fn new(plugins: Vec<Plugin>, media: MediaStore, config: BotConfig) -> Self {
+        for plugin in &plugins {
+            config.permissions.init_acl(&plugin.name, plugin.acl());
+        }
+        let match_unions = Arc::new(extract_match_unions(&plugins));
+        let resolve_reply = match_unions.iter().any(|(_, mu)| mu.matcher.uses_to_me());
+        let plugins = Arc::new(plugins);
+        let (lifecycle, mut lifecycle_rx) = mpsc::unbounded_channel::<LifecycleHooks>();
+        tokio::spawn({
+            let plugins = plugins.clone();
+            // 所有 Dispatcher 被丢弃后通道关闭，任务随之退出
+            async move {
+                while let Some((name, hooks, caller)) = lifecycle_rx.recv().await {
+                    run_hooks(&plugins, name, hooks, caller).await;
+                }
+            }
+        });
+        Self {
+            config: Arc::new(config),
+            media: Arc::new(media),
+            match_unions,
+            resolve_reply,
+            plugins,
+            lifecycle,
+            sessions: Arc::default(),
+            jobs: Arc::default(),
+            stop_jobs: Arc::new(watch::Sender::new(false)),
+            tasks: Arc::default(),
+            fed: Arc::default(),
+            closing: Arc::default(),
+            closed: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    pub(crate) fn dispatch(&self, caller: Arc<dyn Caller>, event: Event) {
+        self.spawn(caller, event, None);
+    }
+
+    /// 分发事件，并返回接收快速操作的通道，处理流程结束且未设置快速操作时通道关闭
+    pub(crate) fn dispatch_with_quick_operation(
+        &self,
+        caller: Arc<dyn Caller>,
+        event: Event,
+    ) -> oneshot::Receiver<QuickOperation> {
+        let (tx, rx) = oneshot::channel();
+        self.spawn(caller, event, Some(Arc::new(Mutex::new(Some(tx)))));
+        rx
+    }
+
+    fn spawn(&self, caller: Arc<dyn Caller>, mut event: Event, quick_operation: Option<QuickOperationSender>) {
+        debug!("Receive event: {event:?}");
+        event.normalize();
+        event.strip_to_me(&self.config.nicknames);
+        let context = Context {
+            caller,
+            event: Arc::new(event),
+            plugins: self.plugins.clone(),
+            extensions: Default::default(),
+            quick_operation,
+            media: self.media.clone(),
+            captured: Arc::default(),
+            config: self.config.clone(),
+            sessions: self.sessions.clone(),
+        };
+        let quick_operation = context.quick_operation.clone();
+        let (sessions, match_unions, resolve_reply) =
+            (self.sessions.clone(), self.match_unions.clone(), self.resolve_reply);
+        let mut tasks = self.tasks.lock().unwrap();
+        // 在持有锁时检查，保证 shutdown 取走的任务集合包含所有已经接收的事件
+        if self.closing.load(Ordering::Acquire) {
+            debug!("Drop event received during shutdown");
+            return;
+        }
+        // 持有 tasks 的锁时取出上一个事件的通道，保证与收到事件的顺序一致
+        let (fed_tx, fed_rx) = oneshot::channel::<()>();
+        let previous = self.fed.lock().unwrap().replace(fed_rx);
+        // 顺便回收已经结束的任务
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(async move {
+            // 先经过中间件，被丢弃的事件不会交给会话，也不会再调用 API
+            let context = before_match(context).await;
+            // 中间件可以并发执行，但需要等待之前的事件交给会话之后再交给会话，避免同一用户连续发送的消息乱序到达
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            let context = context.and_then(|context| sessions.feed(context));
+            drop(fed_tx);
+            if let Some(context) = context {
+                process(context, match_unions, resolve_reply).await;
+            }
+            // 处理流程结束后不再接受快速操作，使上报请求立即得到响应，而不必等待所有 Context 被释放
+            if let Some(sender) = quick_operation {
+                sender.lock().unwrap().take();
+            }
+        });
+    }
+
+    /// 依次调用所有插件的启动钩子，全部完成后开始执行定时任务
+    pub(crate) async fn startup(&self, caller: Arc<dyn Caller>) {
+        run_hooks(&self.plugins, "startup", Plugin::startup_hooks, caller.clone()).await;
+        let mut jobs = self.jobs.lock().unwrap();
+        // 启动钩子执行期间已经开始停止时不再启动定时任务
+        if self.closing.load(Ordering::Acquire) {
+            return;
+        }
+        for plugin in self.plugins.iter() {
+            for job in plugin.jobs() {
+                let (name, job, caller) = (plugin.name.clone(), job.clone(), caller.clone());
+                let stop = self.stop_jobs.subscribe();
+                jobs.spawn(async move { job.run(&name, caller, stop).await });
+            }
+        }
+    }
+
+    /// 停止处理新的事件与定时任务，等待正在执行的处理器与定时任务结束后依次调用所有插件的停止钩子
+    ///
+    /// 多次调用时只有第一次生效，其余调用等待停止流程完成后返回
+    pub(crate) async fn shutdown(&self, caller: Arc<dyn Caller>) {
+        let tasks = {
+            let mut tasks = self.tasks.lock().unwrap();
+            (!self.closing.swap(true, Ordering::AcqRel)).then(|| std::mem::take(&mut *tasks))
+        };
+        let Some(mut tasks) = tasks else {
+            self.closed().await;
+            return;
+        };
+        // 持有 jobs 的锁之后 startup 不会再启动新的定时任务
+        let mut jobs = std::mem::take(&mut *self.jobs.lock().unwrap());
+        self.stop_jobs.send_replace(true);
+        if !tasks.is_empty() || !jobs.is_empty() {
+            info!(
+                "Waiting for {} running handlers and {} scheduled jobs",
+                tasks.len(),
+                jobs.len()
+            );
+        }
+        let wait = async {
+            while tasks.join_next().await.is_some() {}
+            while jobs.join_next().await.is_some() {}
+        };
+        if time::timeout(SHUTDOWN_TIMEOUT, wait).await.is_err() {
+            warn!(
+                "{} handlers and {} scheduled jobs still running after {SHUTDOWN_TIMEOUT:?}, aborting",
+                tasks.len(),
+                jobs.len()
+            );
+            tasks.shutdown().await;
+            jobs.shutdown().await;
+        }
+        if time::timeout(
+            SHUTDOWN_TIMEOUT,
+            run_hooks(&self.plugins, "shutdown", Plugin::shutdown_hooks, caller),
+        )
+        .await
+        .is_err()
+        {
+            warn!("Shutdown hooks still running after {SHUTDOWN_TIMEOUT:?}, skipping the rest");
+        }
+        self.closed.send_replace(true);
+    }
+
+    /// 等待停止流程完成
+    pub(crate) async fn closed(&self) {
+        // 发送端与 Dispatcher 一同存活，wait_for 不会失败
+        let _ = self.closed.subscribe().wait_for(|closed| *closed).await;
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    /// 依次调用所有插件的连接建立钩子
+    pub(crate) fn bot_connected(&self, caller: Arc<dyn Caller>) {
+        self.spawn_hooks("bot_connect", Plugin::bot_connect_hooks, caller);
+    }
+
+    /// 依次调用所有插件的连接断开钩子
+    pub(crate) fn bot_disconnected(&self, caller: Arc<dyn Caller>) {
+        self.spawn_hooks("bot_disconnect", Plugin::bot_disconnect_hooks, caller);
+    }
+
+    fn spawn_hooks(&self, name: &'static str, hooks: fn(&Plugin) -> &[Hook], caller: Arc<dyn Caller>) {
+        // 接收任务与 Dispatcher 同时存活，发送不会失败
+        let _ = self.lifecycle.send((name, hooks, caller));
+    }
+}
+
+async fn run_hooks(plugins: &[Plugin], name: &str, hooks: fn(&Plugin) -> &[Hook], caller: Arc<dyn Caller>) {
+    for plugin in plugins {
+        for hook in hooks(plugin) {
+            if let Err(e) = hook(caller.clone()).await {
+                error!("Failed to run {name} hook of {}: {:?}", plugin.name, e);
+            }
+        }
+    }
+}
+
+/// 依次调用中间件的 before_match，事件被丢弃时返回 None
+async fn before_match(mut context: Context) -> Option<Context> {
+    for middleware in context.config.clone().middlewares.iter() {
+        match middleware.before_match(&mut context).await {
+            Ok(true) => (),
+            Ok(false) => return None,
+            Err(e) => {
+                error!("Failed to run middleware {middleware:?}: {e:?}");
+                return None;
+            }
+        }
+    }
+    Some(context)
+}
+
+/// 按照优先级顺序匹配并处理事件
+async fn process(
+    mut context: Context,
+    match_unions: Arc<Vec<(Cow<'static, str>, Arc<MatchUnion>)>>,
+    resolve_reply: bool,
+) {
+    if resolve_reply
+        && let Some(message_id) = context.event.reply_id()
+        && !context.event.is_to_me()
+        && is_reply_to_me(context.caller.as_ref(), message_id, context.event.self_id()).await
+    {
+        // 中间件可能保留了事件的引用，此时复制一份再修改
+        Arc::make_mut(&mut context.event).set_to_me();
+    }
+    // 按照优先级顺序匹配并处理事件
+    let config = context.config.clone();
+    let scope = Scope::from_event(&context.event);
+    for (plugin, match_union) in match_unions.iter() {
+        if scope.is_some_and(|scope| !config.switches.is_enabled(scope, plugin))
+            || !config.permissions.is_allowed(plugin, &context.event)
+        {
+            continue;
+        }
+        if let Some(captured) = match_union.matcher.capture(&context.event, &config.permissions) {
+            // 超出限制时只回复等待时间，不影响优先级更低的处理器
+            if let Some(wait) = captured.limited {
+                if context.event.try_message().is_ok()
+                    && let Err(e) = context
+                        .reply(format!("操作太频繁，请在 {} 秒后重试", wait.as_secs_f64().ceil()))
+                        .await
+                {
+                    error!("Failed to reply rate limit: {e:?}");
+                }
+                continue;
+            }
+            // 检查之后其他事件占满了次数，视为不匹配
+            if captured.commit_hits().is_err() {
+                continue;
+            }
+            let context = Context {
+                captured: Arc::new(captured),
+                ..context.clone()
+            };
+            match Next::new(plugin, match_union, &config.middlewares).run(context).await {
+                // 事件的返回值被视为中断标志，如果返回 true
+                Err(e) => {
+                    error!("Failed to handle event with {}: {:?}", match_union.matcher, e);
+                    break;
+                }
+                Ok(true) => break,
+                _ => (),
+            }
+        }
+    }
+}
+
+/// 回复的消息是否由 bot 发送，获取失败时视为否
+async fn is_reply_to_me(caller: &dyn Caller, message_id: i32, self_id: u64) -> bool {
+    match caller.get_msg(GetMsgParams { message_id }).await {
+        Ok(message) => message.sender.get("user_id").and_then(|id| id.as_u64()) == Some(self_id),
+        Err(e) => {
+            warn!("Failed to get replied message {message_id}: {e:?}");
+            false
+        }
+    }
+}
+
+fn extract_match_unions(plugins: &[Plugin]) -> Vec<(Cow<'static, str>, Arc<MatchUnion>)> {
+    // 每个插件都有自己的 MatchUnion，但处理时不按插件分割，而是统一按照优先级排序处理
+    // 将排序过程提前，避免在处理任务中重复排序（引入的代价就是 MatchUnion 需要用 Arc 包装）
+    let mut match_unions = plugins
+        .iter()
+        .flat_map(|plugin| plugin.match_unions().iter().map(|mu| (plugin.name.clone(), mu.clone())))
+        .collect::<Vec<_>>();
+    // 优先级从大到小排序
+    match_unions.sort_by_key(|(_, mu)| -mu.priority);
+    match_unions
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        chain::{Matcher, NoopCaller, message},
+        media::MediaTransport,
+        middleware::Middleware,
+    };
+
+    /// 丢弃 user_id 为 3 的用户发送的所有事件
+    struct Blacklist;
+
+    #[async_trait]
+    impl Middleware for Blacklist {
+        async fn before_match(&self, context: &mut Context) -> anyhow::Result<bool> {
+            Ok(context.event.try_user_id().ok() != Some(3))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_before_session() {
+        let mut config = BotConfig::default();
+        config.middlewares.push(Arc::new(Blacklist));
+        let media = MediaStore::new(MediaTransport::Base64).await.unwrap();
+        let dispatcher = Dispatcher::new(Vec::new(), media, config);
+        let caller: Arc<dyn Caller> = Arc::new(NoopCaller);
+        let mut blocked = dispatcher.sessions.wait(None, 3, Matcher::default());
+        let allowed = dispatcher.sessions.wait(None, 2, Matcher::default());
+        dispatcher.dispatch(caller.clone(), message(None, 3, "a"));
+        dispatcher.dispatch(caller.clone(), message(None, 2, "b"));
+        assert_eq!(allowed.await.unwrap().event.plain_text(), "b");
+        // 被中间件丢弃的消息不会交给会话
+        dispatcher.shutdown(caller).await;
+        assert!(blocked.try_recv().is_err());
+    }
+}
@@ -6,6 +6,10 @@ pub enum ConnectError {
     Status(&'static str),
     #[error("Call api timeout")]
     Timeout,
+    #[error("Connect timeout")]
+    ConnectTimeout,
     #[error("WebSocket error")]
     WebSocket,
+    #[error("Connection lost before response arrived")]
+    Disconnected,
 }
@@ -0,0 +1,261 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use http::{Method, Request, Response, StatusCode, header};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use serde_json::{Value, json};
+use sha1::Sha1;
+use tokio::{net::TcpListener, time};
+
+use crate::{
+    adapter::{Adapter, Caller, ConnectOptions, Connector, Dispatcher, ShutdownSignal, error::ConnectError},
+    bot::BotConfig,
+    media::{MediaStore, MediaTransport},
+    plugin::Plugin,
+    schema::*,
+};
+
+/// OneBot 实现等待上报响应的时间有限，超过该时间后不再等待快速操作
+const QUICK_OPERATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP 适配器，通过 HTTP API 调用 OneBot 实现，并在本地监听 HTTP POST 上报的事件
+pub struct HttpAdapter {
+    client: reqwest::Client,
+    api_address: String,
+    listener: Option<TcpListener>,
+    secret: Option<String>,
+    media: MediaTransport,
+}
+
+impl HttpAdapter {
+    pub async fn bind(api_address: &str, listen_address: &str) -> Result<Box<Self>> {
+        Self::bind_with_options(api_address, listen_address, ConnectOptions::default()).await
+    }
+
+    pub async fn bind_with_options(
+        api_address: &str,
+        listen_address: &str,
+        options: ConnectOptions,
+    ) -> Result<Box<Self>> {
+        let mut client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .default_headers(options.header_map()?);
+        if let Some(connect_timeout) = options.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        if let Some(tls) = &options.tls {
+            client = client.use_preconfigured_tls((**tls).clone());
+        }
+        Ok(Box::new(HttpAdapter {
+            client: client.build()?,
+            api_address: api_address.trim_end_matches('/').to_owned(),
+            listener: Some(TcpListener::bind(listen_address).await?),
+            secret: options.secret,
+            media: options.media,
+        }))
+    }
+}
+
+#[async_trait]
+impl Connector for HttpAdapter {
+    async fn spawn(
+        mut self: Box<Self>,
+        plugins: Vec<Plugin>,
+        config: BotConfig,
+        shutdown: ShutdownSignal,
+    ) -> Result<()> {
+        let listener = self
+            .listener
+            .take()
+            .ok_or(ConnectError::Status("Bot already started"))?;
+        info!("Bot started, listening on {}", listener.local_addr()?);
+        let dispatcher = Dispatcher::new(plugins, MediaStore::new(self.media.clone()).await?, config);
+        let self = Arc::new(*self);
+        dispatcher.startup(self.clone()).await;
+        // HTTP 没有长连接，开始监听上报时视为连接建立，停止监听时视为连接断开
+        dispatcher.bot_connected(self.clone());
+        tokio::spawn({
+            let (dispatcher, caller) = (dispatcher.clone(), self.clone());
+            async move {
+                shutdown.await;
+                dispatcher.bot_disconnected(caller.clone());
+                dispatcher.shutdown(caller).await;
+            }
+        });
+        let res = loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => break Err(e.into()),
+                },
+                _ = dispatcher.closed() => break Ok(()),
+            };
+            let adapter = self.clone();
+            let dispatcher = dispatcher.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| handle_request(request, adapter.clone(), dispatcher.clone()));
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    error!("Connection from {peer} exited: {e:?}");
+                }
+            });
+        };
+        if res.is_err() {
+            dispatcher.bot_disconnected(self.clone());
+        }
+        dispatcher.shutdown(self).await;
+        info!("Bot stopped");
+        res
+    }
+}
+
+#[async_trait]
+impl Caller for HttpAdapter {
+    async fn call_raw(&self, payload: ApiRequest) -> Result<ApiResponse> {
+        let echo = payload.echo();
+        let mut request = serde_json::to_value(&payload)?;
+        let action = request["action"]
+            .as_str()
+            .ok_or(ConnectError::Status("Missing action"))?
+            .to_owned();
+        let params = request.get_mut("params").map(Value::take).unwrap_or_else(|| json!({}));
+        let mut response = self
+            .client
+            .post(format!("{}/{}", self.api_address, action))
+            .json(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+        // HTTP API 的响应中没有 echo，补上后复用 ApiResponse 的反序列化
+        response["echo"] = echo.into();
+        Ok(serde_json::from_value(response)?)
+    }
+}
+
+#[async_trait]
+impl Adapter for HttpAdapter {}
+
+async fn handle_request(
+    request: Request<Incoming>,
+    adapter: Arc<HttpAdapter>,
+    dispatcher: Dispatcher,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if request.method() != Method::POST {
+        return Ok(empty_response(StatusCode::METHOD_NOT_ALLOWED));
+    }
+    let signature = request
+        .headers()
+        .get("X-Signature")
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned);
+    let body = match request.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(e) => {
+            error!("Failed to read request body: {e:?}");
+            return Ok(empty_response(StatusCode::BAD_REQUEST));
+        }
+    };
+    if let Some(secret) = &adapter.secret
+        && !verify_signature(secret, &body, signature.as_deref())
+    {
+        warn!("Reject event with invalid signature");
+        return Ok(empty_response(StatusCode::FORBIDDEN));
+    }
+    let Ok(event) = serde_json::from_slice::<Event>(&body) else {
+        warn!("Receive unknown message: {}", String::from_utf8_lossy(&body));
+        return Ok(empty_response(StatusCode::NO_CONTENT));
+    };
+    let quick_operation = dispatcher.dispatch_with_quick_operation(adapter as Arc<dyn Caller>, event);
+    // 匹配与处理流程结束且未设置快速操作时通道关闭，此时直接返回空响应
+    match time::timeout(QUICK_OPERATION_TIMEOUT, quick_operation).await {
+        Ok(Ok(operation)) => match serde_json::to_vec(&operation) {
+            Ok(body) => Ok(Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from(body)))
+                .unwrap()),
+            Err(e) => {
+                error!("Failed to serialize quick operation: {e:?}");
+                Ok(empty_response(StatusCode::NO_CONTENT))
+            }
+        },
+        _ => Ok(empty_response(StatusCode::NO_CONTENT)),
+    }
+}
+
+fn empty_response(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
+
+/// 校验形如 `sha1=<hex>` 的签名，签名内容为使用 secret 对请求体计算的 HMAC-SHA1
+fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|signature| signature.strip_prefix("sha1="))
+        .and_then(decode_hex)
+    else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00ff7a"), Some(vec![0x00, 0xff, 0x7a]));
+        assert_eq!(decode_hex("ABcd"), Some(vec![0xab, 0xcd]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        // 多字节字符不能被切开
+        assert_eq!(decode_hex("é0"), None);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{"post_type":"meta_event"}"#;
+        let mut mac = Hmac::<Sha1>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let hex = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        assert!(verify_signature("secret", body, Some(&format!("sha1={hex}"))));
+        assert!(verify_signature(
+            "secret",
+            body,
+            Some(&format!("sha1={}", hex.to_uppercase()))
+        ));
+        assert!(!verify_signature("other", body, Some(&format!("sha1={hex}"))));
+        assert!(!verify_signature("secret", b"{}", Some(&format!("sha1={hex}"))));
+        assert!(!verify_signature("secret", body, Some(&hex)));
+        assert!(!verify_signature("secret", body, Some("sha1=not-hex")));
+        assert!(!verify_signature("secret", body, None));
+    }
+}
@@ -1,58 +1,185 @@
-use std::sync::Arc;
+use std::{future::Future, pin::Pin};
 
 use anyhow::Result;
 use async_trait::async_trait;
+mod dispatcher;
 mod error;
+mod http;
+mod options;
+mod reverse_ws;
 mod ws;
 
+pub(crate) use dispatcher::Dispatcher;
+pub use http::HttpAdapter;
+pub use options::ConnectOptions;
+pub use reverse_ws::ReverseWsAdapter;
 pub use ws::WsAdapter;
 
-use crate::{chain::MatchUnion, plugin::Plugin, schema::*};
+use crate::{bot::BotConfig, caller, plugin::Plugin, schema::*};
+
+/// 完成时开始停止 bot，见 `Bot::start_with_shutdown`
+pub type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
 
 #[async_trait]
 pub trait Connector: Send + Sync {
-    async fn spawn(mut self: Box<Self>, plugins: Vec<Plugin>) -> Result<()>;
+    /// 持续处理事件，直到 shutdown 完成且停止流程结束后返回
+    async fn spawn(
+        mut self: Box<Self>,
+        plugins: Vec<Plugin>,
+        config: BotConfig,
+        shutdown: ShutdownSignal,
+    ) -> Result<()>;
 }
 
+/// 各个后端只需要实现 `call_raw`，其余方法均基于 `call_raw` 提供了默认实现
 #[async_trait]
 pub trait Caller: Send + Sync {
-    async fn call(&self, request: ApiRequest) -> Result<ApiResponse>;
-    async fn get_login_info(&self) -> Result<GetLoginInfoResult>;
-    async fn send_private_msg(&self, param: SendPrivateMsgParams) -> Result<SendMsgResult>;
-    async fn send_group_msg(&self, param: SendGroupMsgParams) -> Result<SendMsgResult>;
-    async fn send_msg(&self, param: SendMsgParams) -> Result<SendMsgResult>;
-    async fn delete_msg(&self, param: DeleteMsgParams) -> Result<serde_json::Value>;
-    async fn get_msg(&self, param: GetMsgParams) -> Result<GetMsgResult>;
-    async fn get_forward_msg(&self, param: GetForwardMsgParams) -> Result<GetForwardMsgResult>;
+    /// 发送请求并返回未解析数据的响应
+    async fn call_raw(&self, request: ApiRequest) -> Result<ApiResponse>;
+
+    async fn get_login_info(&self) -> Result<GetLoginInfoResult> {
+        caller::get_login_info(self).await
+    }
+
+    async fn send_private_msg(&self, param: SendPrivateMsgParams) -> Result<SendMsgResult> {
+        caller::send_private_msg(self, param).await
+    }
+
+    async fn send_group_msg(&self, param: SendGroupMsgParams) -> Result<SendMsgResult> {
+        caller::send_group_msg(self, param).await
+    }
+
+    async fn send_msg(&self, param: SendMsgParams) -> Result<SendMsgResult> {
+        caller::send_msg(self, param).await
+    }
+
+    async fn delete_msg(&self, param: DeleteMsgParams) -> Result<serde_json::Value> {
+        caller::delete_msg(self, param).await
+    }
+
+    async fn get_msg(&self, param: GetMsgParams) -> Result<GetMsgResult> {
+        caller::get_msg(self, param).await
+    }
+
+    async fn get_forward_msg(&self, param: GetForwardMsgParams) -> Result<GetForwardMsgResult> {
+        caller::get_forward_msg(self, param).await
+    }
+
+    async fn get_group_list(&self) -> Result<Vec<GroupInfo>> {
+        caller::get_group_list(self).await
+    }
+
+    async fn get_group_info(&self, param: GetGroupInfoParams) -> Result<GroupInfo> {
+        caller::get_group_info(self, param).await
+    }
+
+    async fn get_group_member_info(&self, param: GetGroupMemberInfoParams) -> Result<GroupMemberInfo> {
+        caller::get_group_member_info(self, param).await
+    }
+
+    async fn get_group_member_list(&self, param: GetGroupMemberListParams) -> Result<Vec<GroupMemberInfo>> {
+        caller::get_group_member_list(self, param).await
+    }
+
+    async fn get_friend_list(&self) -> Result<Vec<FriendInfo>> {
+        caller::get_friend_list(self).await
+    }
+
+    async fn get_stranger_info(&self, param: GetStrangerInfoParams) -> Result<StrangerInfo> {
+        caller::get_stranger_info(self, param).await
+    }
+
+    async fn get_status(&self) -> Result<StatusResult> {
+        caller::get_status(self).await
+    }
+
+    async fn get_version_info(&self) -> Result<VersionInfoResult> {
+        caller::get_version_info(self).await
+    }
+
+    async fn set_group_kick(&self, param: SetGroupKickParams) -> Result<serde_json::Value> {
+        caller::set_group_kick(self, param).await
+    }
+
+    async fn set_group_ban(&self, param: SetGroupBanParams) -> Result<serde_json::Value> {
+        caller::set_group_ban(self, param).await
+    }
+
+    async fn set_group_anonymous_ban(&self, param: SetGroupAnonymousBanParams) -> Result<serde_json::Value> {
+        caller::set_group_anonymous_ban(self, param).await
+    }
+
+    async fn set_group_whole_ban(&self, param: SetGroupWholeBanParams) -> Result<serde_json::Value> {
+        caller::set_group_whole_ban(self, param).await
+    }
+
+    async fn set_group_admin(&self, param: SetGroupAdminParams) -> Result<serde_json::Value> {
+        caller::set_group_admin(self, param).await
+    }
+
+    async fn set_group_card(&self, param: SetGroupCardParams) -> Result<serde_json::Value> {
+        caller::set_group_card(self, param).await
+    }
+
+    async fn set_group_name(&self, param: SetGroupNameParams) -> Result<serde_json::Value> {
+        caller::set_group_name(self, param).await
+    }
+
+    async fn set_group_leave(&self, param: SetGroupLeaveParams) -> Result<serde_json::Value> {
+        caller::set_group_leave(self, param).await
+    }
+
+    async fn set_group_special_title(&self, param: SetGroupSpecialTitleParams) -> Result<serde_json::Value> {
+        caller::set_group_special_title(self, param).await
+    }
+
+    async fn set_friend_add_request(&self, param: SetFriendAddRequestParams) -> Result<serde_json::Value> {
+        caller::set_friend_add_request(self, param).await
+    }
+
+    async fn set_group_add_request(&self, param: SetGroupAddRequestParams) -> Result<serde_json::Value> {
+        caller::set_group_add_request(self, param).await
+    }
 
     #[cfg(feature = "napcat")]
-    async fn set_msg_emoji_like(&self, param: SetMsgEmojiLikeParams) -> Result<serde_json::Value>;
+    async fn set_msg_emoji_like(&self, param: SetMsgEmojiLikeParams) -> Result<serde_json::Value> {
+        caller::set_msg_emoji_like(self, param).await
+    }
+
     #[cfg(any(feature = "go-cqhttp", feature = "lagrange"))]
-    async fn set_group_reaction(&self, param: SetGroupReactionParams) -> Result<serde_json::Value>;
+    async fn set_group_reaction(&self, param: SetGroupReactionParams) -> Result<serde_json::Value> {
+        caller::set_group_reaction(self, param).await
+    }
 
     #[cfg(feature = "lagrange")]
-    async fn send_private_forward_msg(&self, param: SendPrivateForwardMsgParams) -> Result<SendMsgResult>;
+    async fn send_private_forward_msg(&self, param: SendPrivateForwardMsgParams) -> Result<SendMsgResult> {
+        caller::send_private_forward_msg(self, param).await
+    }
 
     #[cfg(feature = "lagrange")]
-    async fn send_group_forward_msg(&self, param: SendGroupForwardMsgParams) -> Result<SendMsgResult>;
+    async fn send_group_forward_msg(&self, param: SendGroupForwardMsgParams) -> Result<SendMsgResult> {
+        caller::send_group_forward_msg(self, param).await
+    }
 
     // 注意这个方法在多个后端都有实现，但实现方式不同
     #[cfg(any(feature = "napcat", feature = "go-cqhttp", feature = "lagrange"))]
-    async fn send_forward_msg(&self, param: SendForwardMsgParams) -> Result<SendMsgResult>;
+    async fn send_forward_msg(&self, param: SendForwardMsgParams) -> Result<SendMsgResult> {
+        caller::send_forward_msg(self, param).await
+    }
 }
 
+/// Caller 的泛型方法，为所有 Caller（包括 `dyn Caller`）自动实现
+///
+/// 泛型方法无法放在 Caller 中，否则 Caller 不能作为 trait object 使用
 #[async_trait]
-pub trait Adapter: Connector + Caller {}
-
-pub(crate) fn extract_match_unions(plugins: &[Plugin]) -> Vec<Arc<MatchUnion>> {
-    // 每个插件都有自己的 MatchUnion，但处理时不按插件分割，而是统一按照优先级排序处理
-    // 将排序过程提前，避免在处理任务中重复排序（引入的代价就是 MatchUnion 需要用 Arc 包装）
-    let mut match_unions = plugins
-        .iter()
-        .flat_map(|plugin| plugin.match_unions())
-        .cloned()
-        .collect::<Vec<_>>();
-    // 优先级从大到小排序
-    match_unions.sort_by_key(|mu| -mu.priority);
-    match_unions
+pub trait CallerExt: Caller {
+    /// 调用任意 API，响应数据按照请求参数对应的类型解析
+    async fn call<A: Action>(&self, params: A) -> Result<A::Output> {
+        caller::call(self, params).await
+    }
 }
+
+impl<T: Caller + ?Sized> CallerExt for T {}
+
+#[async_trait]
+pub trait Adapter: Connector + Caller {}
@@ -0,0 +1,127 @@
+use std::{borrow::Cow, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
+use percent_encoding::percent_decode_str;
+
+use crate::media::MediaTransport;
+
+/// 连接 OneBot 实现时使用的选项，各个 Adapter 按需使用其中的字段
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// 与 OneBot 实现中配置的 access_token 一致，为 None 时不进行鉴权
+    ///
+    /// 正向连接时通过 `Authorization: Bearer <access_token>` 发送，反向连接时用于校验 OneBot 实现
+    pub access_token: Option<String>,
+    /// 建立连接时额外携带的请求头
+    pub headers: Vec<(String, String)>,
+    /// 自定义 TLS 配置，为 None 时使用内置的根证书
+    pub tls: Option<Arc<rustls::ClientConfig>>,
+    /// 建立连接的超时时间，为 None 时不超时
+    pub connect_timeout: Option<Duration>,
+    /// HTTP POST 上报的签名密钥，为 None 时不校验 `X-Signature`，仅用于 HttpAdapter
+    pub secret: Option<String>,
+    /// 发送图片、语音等媒体内容时使用的传输方式
+    pub media: MediaTransport,
+}
+
+impl ConnectOptions {
+    /// 正向连接时需要携带的请求头，包括鉴权信息与自定义请求头
+    pub(crate) fn header_map(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        if let Some(access_token) = &self.access_token {
+            headers.insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", access_token.trim()))?,
+            );
+        }
+        for (name, value) in &self.headers {
+            headers.insert(HeaderName::try_from(name)?, HeaderValue::from_str(value)?);
+        }
+        Ok(headers)
+    }
+
+    /// 反向连接时校验 OneBot 实现携带的 access_token，未配置 access_token 时不校验
+    ///
+    /// access_token 可以通过 `Authorization` 请求头或 `access_token` 查询参数传递，查询参数会先进行百分号解码
+    pub(crate) fn verify_access_token(
+        &self,
+        headers: &HeaderMap,
+        query: Option<&str>,
+    ) -> Result<(), (StatusCode, &'static str)> {
+        let Some(access_token) = &self.access_token else {
+            return Ok(());
+        };
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("Token ")))
+            .map(Cow::Borrowed)
+            .or_else(|| {
+                query?
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("access_token="))
+                    .map(|value| percent_decode_str(value).decode_utf8_lossy())
+            });
+        match provided {
+            None => Err((StatusCode::UNAUTHORIZED, "Missing access token")),
+            // 使用常数时间比较，避免通过响应时间逐字节猜测 access_token
+            Some(provided)
+                if !constant_time_eq::constant_time_eq(provided.trim().as_bytes(), access_token.trim().as_bytes()) =>
+            {
+                Err((StatusCode::FORBIDDEN, "Access token mismatch"))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_access_token() {
+        let options = ConnectOptions {
+            access_token: Some("a+b/c d".to_string()),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            options.verify_access_token(&headers, None),
+            Err((StatusCode::UNAUTHORIZED, "Missing access token"))
+        );
+        assert!(
+            options
+                .verify_access_token(&headers, Some("foo=bar&access_token=a%2Bb%2Fc%20d"))
+                .is_ok()
+        );
+        assert_eq!(
+            options.verify_access_token(&headers, Some("access_token=a+b/c")),
+            Err((StatusCode::FORBIDDEN, "Access token mismatch"))
+        );
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer a+b/c d"));
+        assert!(options.verify_access_token(&headers, None).is_ok());
+        assert!(
+            ConnectOptions::default()
+                .verify_access_token(&HeaderMap::new(), None)
+                .is_ok()
+        );
+        // 两侧的首尾空白都会被忽略
+        let options = ConnectOptions {
+            access_token: Some(" secret\n".to_string()),
+            ..Default::default()
+        };
+        let headers = HeaderMap::new();
+        assert!(
+            options
+                .verify_access_token(&headers, Some("access_token=secret"))
+                .is_ok()
+        );
+        assert!(
+            options
+                .verify_access_token(&headers, Some("access_token=%20secret%20"))
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use http::StatusCode;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    task::JoinSet,
+    time,
+};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+use crate::{
+    adapter::{
+        Adapter, Caller, ConnectOptions, Connector, Dispatcher, ShutdownSignal,
+        error::ConnectError,
+        ws::{RequestRecorder, call, serve},
+    },
+    bot::BotConfig,
+    media::MediaStore,
+    plugin::Plugin,
+    schema::*,
+};
+
+/// 停止时等待各个连接关闭的最长时间
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// OneBot 实现连接时通过 `X-Client-Role` 声明的连接用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientRole {
+    /// 同时用于 API 调用与事件推送
+    Universal,
+    /// 仅用于 API 调用
+    Api,
+    /// 仅用于事件推送
+    Event,
+}
+
+/// 一条可用于 API 调用的连接
+#[derive(Clone)]
+struct Connection {
+    /// 用于在连接断开时区分新旧连接
+    id: u64,
+    request_tx: mpsc::Sender<ApiRequest>,
+    request_recorder: RequestRecorder,
+}
+
+type Connections = Arc<DashMap<u64, Connection>>;
+
+/// 反向 WebSocket 适配器，由 OneBot 实现主动连接到 bot，支持同时接入多个账号
+pub struct ReverseWsAdapter {
+    listener: Option<TcpListener>,
+    connections: Connections,
+    options: Arc<ConnectOptions>,
+}
+
+impl ReverseWsAdapter {
+    pub async fn bind(address: &str) -> Result<Box<Self>> {
+        Self::bind_with_options(address, ConnectOptions::default()).await
+    }
+
+    /// 反向连接时只会使用选项中的 access_token 校验 OneBot 实现
+    pub async fn bind_with_options(address: &str, options: ConnectOptions) -> Result<Box<Self>> {
+        Ok(Box::new(ReverseWsAdapter {
+            listener: Some(TcpListener::bind(address).await?),
+            connections: Arc::new(DashMap::new()),
+            options: Arc::new(options),
+        }))
+    }
+}
+
+#[async_trait]
+impl Connector for ReverseWsAdapter {
+    async fn spawn(
+        mut self: Box<Self>,
+        plugins: Vec<Plugin>,
+        config: BotConfig,
+        shutdown: ShutdownSignal,
+    ) -> Result<()> {
+        let listener = self
+            .listener
+            .take()
+            .ok_or(ConnectError::Status("Bot already started"))?;
+        info!("Bot started, listening on {}", listener.local_addr()?);
+        let self = Arc::new(*self);
+        let dispatcher = Dispatcher::new(plugins, MediaStore::new(self.options.media.clone()).await?, config);
+        dispatcher.startup(self.clone()).await;
+        tokio::spawn({
+            let (dispatcher, caller) = (dispatcher.clone(), self.clone());
+            async move {
+                shutdown.await;
+                dispatcher.shutdown(caller).await;
+            }
+        });
+        // 停止后需要等待各个连接发送关闭帧
+        let mut connection_tasks = JoinSet::new();
+        let res = loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => break Err(e.into()),
+                },
+                _ = dispatcher.closed() => break Ok(()),
+            };
+            let connections = self.connections.clone();
+            let options = self.options.clone();
+            let dispatcher = dispatcher.clone();
+            while connection_tasks.try_join_next().is_some() {}
+            connection_tasks.spawn(async move {
+                if let Err(e) = handle_connection(stream, connections, &options, dispatcher).await {
+                    error!("Connection from {peer} exited: {e:?}");
+                }
+            });
+        };
+        dispatcher.shutdown(self).await;
+        if time::timeout(CLOSE_TIMEOUT, connection_tasks.join_all()).await.is_err() {
+            warn!("Some connections are not closed in {CLOSE_TIMEOUT:?}");
+        }
+        info!("Bot stopped");
+        res
+    }
+}
+
+/// 未指定账号时，使用任意一个已连接的账号调用 API
+#[async_trait]
+impl Caller for ReverseWsAdapter {
+    async fn call_raw(&self, payload: ApiRequest) -> Result<ApiResponse> {
+        let connection = self
+            .connections
+            .iter()
+            .next()
+            .map(|entry| entry.value().clone())
+            .ok_or(ConnectError::Status("No OneBot client connected"))?;
+        call(&connection.request_tx, &connection.request_recorder, payload).await
+    }
+}
+
+#[async_trait]
+impl Adapter for ReverseWsAdapter {}
+
+/// 绑定到某个账号的 Caller，事件处理时使用它将请求发回产生事件的账号
+struct ReverseWsCaller {
+    self_id: u64,
+    connections: Connections,
+}
+
+#[async_trait]
+impl Caller for ReverseWsCaller {
+    async fn call_raw(&self, payload: ApiRequest) -> Result<ApiResponse> {
+        // 账号可能分别使用 API 与 Event 两条连接，因此每次调用时都重新查找
+        let connection = self
+            .connections
+            .get(&self.self_id)
+            .map(|entry| entry.value().clone())
+            .ok_or(ConnectError::Status("OneBot client not connected"))?;
+        call(&connection.request_tx, &connection.request_recorder, payload).await
+    }
+}
+
+// 握手回调的错误类型由 tungstenite 决定，无法缩小
+#[allow(clippy::result_large_err)]
+async fn handle_connection(
+    stream: TcpStream,
+    connections: Connections,
+    options: &ConnectOptions,
+    dispatcher: Dispatcher,
+) -> Result<()> {
+    let mut handshake = None;
+    let ws_stream = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        match parse_handshake(request, options) {
+            Ok(res) => {
+                handshake = Some(res);
+                Ok(response)
+            }
+            Err((status, reason)) => {
+                warn!("Reject connection: {reason}");
+                let mut response = ErrorResponse::new(Some(reason.to_owned()));
+                *response.status_mut() = status;
+                Err(response)
+            }
+        }
+    })
+    .await?;
+    let (self_id, role) = handshake.ok_or(ConnectError::WebSocket)?;
+    info!("OneBot client {self_id} connected as {role:?}");
+    let (request_tx, mut request_rx) = mpsc::channel(32);
+    let request_recorder: RequestRecorder = Arc::new(DashMap::new());
+    let connection_id = rand::random::<u64>();
+    let caller = Arc::new(ReverseWsCaller {
+        self_id,
+        connections: connections.clone(),
+    });
+    // 只有可以调用 API 的连接才会登记，并触发连接相关的钩子
+    let callable = role != ClientRole::Event;
+    if callable {
+        connections.insert(
+            self_id,
+            Connection {
+                id: connection_id,
+                request_tx: request_tx.clone(),
+                request_recorder: request_recorder.clone(),
+            },
+        );
+        dispatcher.bot_connected(caller.clone());
+    }
+    let res = serve(
+        ws_stream,
+        &mut request_rx,
+        &request_recorder,
+        caller.clone(),
+        dispatcher.clone(),
+    )
+    .await;
+    // 同一账号可能已经建立了新的连接，只移除属于当前连接的记录
+    connections.remove_if(&self_id, |_, connection| connection.id == connection_id);
+    // 让正在等待响应的调用立即失败
+    request_recorder.clear();
+    // Event 连接不会登记 request_tx，需要保持其存活到连接结束，否则发送任务会立即退出
+    drop(request_tx);
+    if callable {
+        dispatcher.bot_disconnected(caller);
+    }
+    info!("OneBot client {self_id} disconnected");
+    res
+}
+
+fn parse_handshake(
+    request: &Request,
+    options: &ConnectOptions,
+) -> Result<(u64, ClientRole), (StatusCode, &'static str)> {
+    options.verify_access_token(request.headers(), request.uri().query())?;
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+    let self_id = header("X-Self-ID")
+        .and_then(|value| value.parse().ok())
+        .ok_or((StatusCode::BAD_REQUEST, "Missing or invalid X-Self-ID header"))?;
+    // 部分实现不会携带 X-Client-Role，此时按照 Universal 处理
+    let role = match header("X-Client-Role") {
+        None => ClientRole::Universal,
+        Some(role) if role.eq_ignore_ascii_case("Universal") => ClientRole::Universal,
+        Some(role) if role.eq_ignore_ascii_case("API") => ClientRole::Api,
+        Some(role) if role.eq_ignore_ascii_case("Event") => ClientRole::Event,
+        Some(_) => return Err((StatusCode::BAD_REQUEST, "Invalid X-Client-Role header")),
+    };
+    Ok((self_id, role))
+}
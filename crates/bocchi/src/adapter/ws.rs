@@ -1,223 +1,308 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use http::Uri;
-use tokio::{net::TcpStream, sync::oneshot::Sender, time};
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::{mpsc, oneshot::Sender},
+    time,
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream,
+    tungstenite::{Message, client::IntoClientRequest},
+};
 
 use crate::{
-    adapter::{Adapter, Caller, Connector, error::ConnectError, extract_match_unions},
-    caller::*,
-    chain::Context,
+    adapter::{Adapter, Caller, ConnectOptions, Connector, Dispatcher, ShutdownSignal, error::ConnectError},
+    bot::BotConfig,
+    media::MediaStore,
     plugin::Plugin,
     schema::*,
 };
 
+/// 断线重连的初始等待时间，每次失败后翻倍
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// 断线重连的最大等待时间
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub(crate) type RequestRecorder = Arc<DashMap<u64, Sender<ApiResponse>>>;
+
 #[derive(Debug)]
 pub struct WsAdapter {
+    address: Uri,
+    options: ConnectOptions,
     ws_stream: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    request_recorder: Arc<DashMap<u64, Sender<ApiResponse>>>,
-    request_tx: Option<tokio::sync::mpsc::Sender<ApiRequest>>,
+    request_recorder: RequestRecorder,
+    request_tx: Option<mpsc::Sender<ApiRequest>>,
+    connected: AtomicBool,
 }
 
 impl WsAdapter {
     pub async fn connect(address: &str) -> Result<Box<Self>> {
-        let (ws_stream, _) = tokio_tungstenite::connect_async(Uri::from_str(address)?).await?;
+        Self::connect_with_options(address, ConnectOptions::default()).await
+    }
+
+    pub async fn connect_with_options(address: &str, options: ConnectOptions) -> Result<Box<Self>> {
+        let address = Uri::from_str(address)?;
+        let ws_stream = open(&address, &options).await?;
         Ok(Box::new(WsAdapter {
+            address,
+            options,
             ws_stream: Some(ws_stream),
             request_recorder: Arc::new(DashMap::new()),
             request_tx: None,
+            connected: AtomicBool::new(false),
         }))
     }
+
+    /// 按照指数退避不断尝试重连，直到连接成功
+    async fn reconnect(&self) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+        let mut attempt = 0;
+        loop {
+            let backoff = backoff(attempt);
+            info!("Reconnecting in {backoff:?}");
+            time::sleep(backoff).await;
+            match open(&self.address, &self.options).await {
+                Ok(ws_stream) => {
+                    info!("Reconnected to {}", self.address);
+                    return ws_stream;
+                }
+                Err(e) => {
+                    error!("Failed to reconnect: {e:?}");
+                    attempt += 1;
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl Connector for WsAdapter {
-    async fn spawn(mut self: Box<Self>, plugins: Vec<Plugin>) -> Result<()> {
-        let ws_stream = self.ws_stream.take().ok_or(ConnectError::WebSocket)?;
+    async fn spawn(
+        mut self: Box<Self>,
+        plugins: Vec<Plugin>,
+        config: BotConfig,
+        shutdown: ShutdownSignal,
+    ) -> Result<()> {
+        let mut ws_stream = self.ws_stream.take().ok_or(ConnectError::WebSocket)?;
         info!("Bot started");
-        // 进行一些全局初始化工作
-        let (request_tx, mut request_rx) = tokio::sync::mpsc::channel(32);
+        // 进行一些全局初始化工作，请求通道与 request_recorder 在重连前后保持不变
+        let (request_tx, mut request_rx) = mpsc::channel(32);
         self.request_tx = Some(request_tx);
-        let (mut ws_sink, mut ws_stream) = ws_stream.split();
-        // 初始化发送请求任务
-        let (sender_res_tx, sender_res_rx) = tokio::sync::oneshot::channel::<Result<()>>();
-        // 启动发送请求任务
-        tokio::spawn(async move {
-            let e = async {
-                while let Some(msg) = request_rx.recv().await {
-                    // 从请求通道中接收请求，发送到 websocket 服务器
-                    ws_sink.send(Message::text(serde_json::to_string(&msg)?)).await?;
-                }
-                // magic from https://rust-lang.github.io/async-book/07_workarounds/02_err_in_async_blocks.html
-                Ok::<_, anyhow::Error>(())
-            }
-            .await;
-            let _ = sender_res_tx.send(e);
-        });
-        // 初始化接收消息任务
         let request_recorder = self.request_recorder.clone();
         let self = Arc::new(*self);
-        let match_unions = Arc::new(extract_match_unions(&plugins));
-        let plugins = Arc::new(plugins);
-        let (receiver_res_tx, receiver_res_rx) = tokio::sync::oneshot::channel::<Result<()>>();
-        // 启动接收消息任务
-        tokio::spawn(async move {
-            let e = async {
-                while let Some(msg) = ws_stream.next().await {
-                    let msg = msg?;
-                    match msg {
-                        Message::Close(_) => {
-                            error!("Connection closed");
-                            break;
-                        }
-                        Message::Text(text) => {
-                            if let Ok(resp) = serde_json::from_str::<ApiResponse>(&text) {
-                                if let Some((_, tx)) = request_recorder.remove(&resp.echo()) {
-                                    if let Err(e) = tx.send(resp) {
-                                        error!("Failed to send response: {e:?}");
-                                    }
-                                } else {
-                                    error!("Received response with unknown request ID: {text}");
-                                }
-                            } else if let Ok(event) = serde_json::from_str::<Event>(&text) {
-                                debug!("Receive event: {event:?}");
-                                let context = Context {
-                                    caller: self.clone() as Arc<dyn Caller>,
-                                    event: Arc::new(event),
-                                    plugins: plugins.clone(),
-                                };
-                                let match_unions_clone = match_unions.clone();
-                                tokio::spawn(async move {
-                                    // 按照优先级顺序匹配并处理事件
-                                    for match_union in match_unions_clone.iter() {
-                                        if match_union.matcher.is_match(&context.event) {
-                                            match (*match_union.handler)(context.clone()).await {
-                                                // 事件的返回值被视为中断标志，如果返回 true
-                                                Err(e) => {
-                                                    error!(
-                                                        "Failed to handle event with {}: {:?}",
-                                                        match_union.matcher, e
-                                                    );
-                                                    break;
-                                                }
-                                                Ok(true) => break,
-                                                _ => (),
-                                            }
-                                        }
-                                    }
-                                });
-                            } else {
-                                warn!("Receive unknown message: {text}");
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-                Ok::<_, anyhow::Error>(())
+        let dispatcher = Dispatcher::new(plugins, MediaStore::new(self.options.media.clone()).await?, config);
+        dispatcher.startup(self.clone()).await;
+        // 停止期间连接保持可用，处理器与停止钩子仍然可以调用 API，全部完成后由 serve 关闭连接
+        tokio::spawn({
+            let (dispatcher, caller) = (dispatcher.clone(), self.clone());
+            async move {
+                shutdown.await;
+                dispatcher.shutdown(caller).await;
             }
-            .await;
-            let _ = receiver_res_tx.send(e);
         });
-        let res = tokio::select! {
-            res = sender_res_rx => {
-                let res = res?;
-                error!("Send request task exited: {res:?}");
-                res
-            },
-            res = receiver_res_rx => {
-                let res = res?;
-                error!("Receive message task exited: {res:?}");
-                res
-            },
-        };
-        res
+        loop {
+            self.connected.store(true, Ordering::Release);
+            dispatcher.bot_connected(self.clone());
+            let res = serve(
+                ws_stream,
+                &mut request_rx,
+                &request_recorder,
+                self.clone(),
+                dispatcher.clone(),
+            )
+            .await;
+            self.connected.store(false, Ordering::Release);
+            if dispatcher.is_closed() {
+                info!("Bot stopped");
+                return Ok(());
+            }
+            error!("Connection lost: {res:?}");
+            // 丢弃尚未发出的请求，并让正在等待响应的调用立即失败
+            while request_rx.try_recv().is_ok() {}
+            request_recorder.clear();
+            dispatcher.bot_disconnected(self.clone());
+            ws_stream = tokio::select! {
+                ws_stream = self.reconnect() => ws_stream,
+                _ = dispatcher.closed() => {
+                    info!("Bot stopped");
+                    return Ok(());
+                }
+            };
+        }
     }
 }
 
 #[async_trait]
 impl Caller for WsAdapter {
-    async fn call(&self, payload: ApiRequest) -> Result<ApiResponse> {
+    async fn call_raw(&self, payload: ApiRequest) -> Result<ApiResponse> {
         let request_tx = self
             .request_tx
             .as_ref()
             .ok_or(ConnectError::Status("Bot not started"))?;
-        let (tx, rx) = tokio::sync::oneshot::channel::<ApiResponse>();
-        let echo = payload.echo();
-        self.request_recorder.insert(echo, tx);
-        let res = async {
-            request_tx.send(payload).await?;
-            tokio::select! {
-                response = rx => {
-                    Ok(response?)
-                }
-                _ = time::sleep(time::Duration::from_secs(30)) => {
-                    Err(ConnectError::Timeout.into())
-                }
-            }
+        if !self.connected.load(Ordering::Acquire) {
+            return Err(ConnectError::Disconnected.into());
         }
-        .await;
-        // no matter success or failure, remove the request from the recorder
-        self.request_recorder.remove(&echo);
-        res
-    }
-
-    async fn get_login_info(&self) -> Result<GetLoginInfoResult> {
-        get_login_info(self as &dyn Caller).await
-    }
-
-    async fn send_private_msg(&self, param: SendPrivateMsgParams) -> Result<SendMsgResult> {
-        send_private_msg(self as &dyn Caller, param).await
-    }
-
-    async fn send_group_msg(&self, param: SendGroupMsgParams) -> Result<SendMsgResult> {
-        send_group_msg(self as &dyn Caller, param).await
-    }
-
-    async fn send_msg(&self, param: SendMsgParams) -> Result<SendMsgResult> {
-        send_msg(self as &dyn Caller, param).await
-    }
-
-    async fn delete_msg(&self, param: DeleteMsgParams) -> Result<serde_json::Value> {
-        delete_msg(self as &dyn Caller, param).await
+        call(request_tx, &self.request_recorder, payload).await
     }
+}
 
-    async fn get_msg(&self, param: GetMsgParams) -> Result<GetMsgResult> {
-        get_msg(self as &dyn Caller, param).await
-    }
+#[async_trait]
+impl Adapter for WsAdapter {}
 
-    async fn get_forward_msg(&self, param: GetForwardMsgParams) -> Result<GetForwardMsgResult> {
-        get_forward_msg(self as &dyn Caller, param).await
-    }
+/// 按照连接选项建立 WebSocket 连接
+async fn open(address: &Uri, options: &ConnectOptions) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let mut request = address.clone().into_client_request()?;
+    request.headers_mut().extend(options.header_map()?);
+    let connector = options.tls.clone().map(tokio_tungstenite::Connector::Rustls);
+    let connect = tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector);
+    let (ws_stream, _) = match options.connect_timeout {
+        Some(timeout) => time::timeout(timeout, connect)
+            .await
+            .map_err(|_| ConnectError::ConnectTimeout)??,
+        None => connect.await?,
+    };
+    Ok(ws_stream)
+}
 
-    #[cfg(feature = "napcat")]
-    async fn set_msg_emoji_like(&self, param: SetMsgEmojiLikeParams) -> Result<serde_json::Value> {
-        set_msg_emoji_like(self as &dyn Caller, param).await
+/// 通过 WebSocket 连接发送请求，并等待对应 echo 的响应
+pub(crate) async fn call(
+    request_tx: &mpsc::Sender<ApiRequest>,
+    request_recorder: &RequestRecorder,
+    payload: ApiRequest,
+) -> Result<ApiResponse> {
+    let (tx, rx) = tokio::sync::oneshot::channel::<ApiResponse>();
+    let echo = payload.echo();
+    request_recorder.insert(echo, tx);
+    let res = async {
+        request_tx.send(payload).await?;
+        tokio::select! {
+            response = rx => {
+                // 连接断开时 request_recorder 会被清空，发送端随之被丢弃
+                response.map_err(|_| ConnectError::Disconnected.into())
+            }
+            _ = time::sleep(time::Duration::from_secs(30)) => {
+                Err(ConnectError::Timeout.into())
+            }
+        }
     }
+    .await;
+    // no matter success or failure, remove the request from the recorder
+    request_recorder.remove(&echo);
+    res
+}
 
-    #[cfg(any(feature = "napcat", feature = "go-cqhttp", feature = "lagrange"))]
-    async fn send_forward_msg(&self, param: SendForwardMsgParams) -> Result<SendMsgResult> {
-        send_forward_msg(self as &dyn Caller, param).await
+/// 在一条已经建立的 WebSocket 连接上收发数据，直到连接断开
+///
+/// 正向与反向 WebSocket 只是建立连接的方式不同，连接建立后的处理逻辑完全一致
+pub(crate) async fn serve<S>(
+    ws_stream: WebSocketStream<S>,
+    request_rx: &mut mpsc::Receiver<ApiRequest>,
+    request_recorder: &RequestRecorder,
+    caller: Arc<dyn Caller>,
+    dispatcher: Dispatcher,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut ws_sink, mut ws_stream) = ws_stream.split();
+    // 发送请求任务，bot 停止后发送关闭帧
+    let send = async {
+        loop {
+            tokio::select! {
+                msg = request_rx.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    // 从请求通道中接收请求，发送到 websocket 服务器
+                    ws_sink.send(Message::text(serde_json::to_string(&msg)?)).await?;
+                }
+                _ = dispatcher.closed() => {
+                    ws_sink.close().await?;
+                    break;
+                }
+            }
+        }
+        // magic from https://rust-lang.github.io/async-book/07_workarounds/02_err_in_async_blocks.html
+        Ok::<_, anyhow::Error>(())
+    };
+    // 接收消息任务
+    let receive = async {
+        while let Some(msg) = ws_stream.next().await {
+            let msg = msg?;
+            match msg {
+                Message::Close(_) => {
+                    error!("Connection closed");
+                    break;
+                }
+                Message::Text(text) => {
+                    if let Ok(resp) = serde_json::from_str::<ApiResponse>(&text) {
+                        if let Some((_, tx)) = request_recorder.remove(&resp.echo()) {
+                            if let Err(e) = tx.send(resp) {
+                                error!("Failed to send response: {e:?}");
+                            }
+                        } else {
+                            error!("Received response with unknown request ID: {text}");
+                        }
+                    } else if let Ok(event) = serde_json::from_str::<Event>(&text) {
+                        dispatcher.dispatch(caller.clone(), event);
+                    } else {
+                        warn!("Receive unknown message: {text}");
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    // 任意一方退出即认为连接已经不可用
+    tokio::select! {
+        res = send => {
+            if !dispatcher.is_closed() {
+                error!("Send request task exited: {res:?}");
+            }
+            res
+        },
+        res = receive => {
+            error!("Receive message task exited: {res:?}");
+            res
+        },
     }
+}
 
-    #[cfg(feature = "lagrange")]
-    async fn send_private_forward_msg(&self, param: SendPrivateForwardMsgParams) -> Result<SendMsgResult> {
-        send_private_forward_msg(self as &dyn Caller, param).await
-    }
+/// 计算第 attempt 次重连前的等待时间，在指数退避的基础上加入随机抖动，避免多个实例同时重连
+fn backoff(attempt: u32) -> Duration {
+    let backoff = INITIAL_BACKOFF
+        .checked_mul(2u32.saturating_pow(attempt))
+        .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF));
+    backoff.mul_f64(rand::random_range(0.5..=1.0))
+}
 
-    #[cfg(feature = "lagrange")]
-    async fn send_group_forward_msg(&self, param: SendGroupForwardMsgParams) -> Result<SendMsgResult> {
-        send_group_forward_msg(self as &dyn Caller, param).await
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    #[cfg(any(feature = "go-cqhttp", feature = "lagrange"))]
-    async fn set_group_reaction(&self, param: SetGroupReactionParams) -> Result<serde_json::Value> {
-        set_group_reaction(self as &dyn Caller, param).await
+    #[test]
+    fn test_backoff() {
+        for _ in 0..100 {
+            let first = backoff(0);
+            assert!(first >= INITIAL_BACKOFF / 2 && first <= INITIAL_BACKOFF);
+            let fourth = backoff(3);
+            assert!(fourth >= INITIAL_BACKOFF * 4 && fourth <= INITIAL_BACKOFF * 8);
+            // 次数很大时不会溢出，且不超过最大等待时间
+            let capped = backoff(u32::MAX);
+            assert!(capped >= MAX_BACKOFF / 2 && capped <= MAX_BACKOFF);
+        }
     }
 }
-
-#[async_trait]
-impl Adapter for WsAdapter {}
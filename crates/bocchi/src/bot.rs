@@ -1,25 +1,120 @@
-use std::{borrow::Cow, future::Future};
+use std::{borrow::Cow, future::Future, path::PathBuf, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use http::Extensions;
 
 use crate::{
-    adapter::{self, Adapter},
-    chain::{Context, Matcher, Rule},
+    adapter::{self, Adapter, ConnectOptions},
+    chain::{Context, IntoHandler, Matcher, Rule},
+    middleware::Middleware,
+    permission::Permissions,
     plugin::Plugin,
-    schema::{MessageContent, MessageSegment, SendForwardMsgParams},
+    schema::{MessageContent, MessageSegment, Role},
+    switch::{PluginSwitches, Scope},
 };
 
+/// 与连接方式无关的 bot 配置，启动时交给 Adapter
+#[derive(Debug, Clone, Default)]
+pub struct BotConfig {
+    /// bot 的昵称，以昵称开头的消息会被视为对 bot 说的，见 `Rule::to_me`
+    pub nicknames: Vec<String>,
+    /// 超级用户与插件的访问控制列表，运行期间可以通过 `Context::permissions` 修改
+    pub permissions: Arc<Permissions>,
+    /// 各个群组与私聊中插件的启用状态
+    pub switches: Arc<PluginSwitches>,
+    /// 处理器通过 `State<T>` 获取的共享状态，以 `Arc<T>` 的形式保存
+    pub state: Extensions,
+    /// 按照注册顺序调用的中间件
+    pub middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+/// 等待 SIGINT（Ctrl-C）或 SIGTERM，非 Unix 平台只等待 Ctrl-C
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {e:?}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {e:?}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => (),
+        _ = terminate => (),
+    }
+}
+
+/// 直接注册在 Bot 上的插件，包含插件管理命令，不允许被禁用
+const BUILTIN_PLUGIN: &str = "内建插件";
+
 pub struct Bot {
     adapter: Box<dyn Adapter>,
     plugins: Vec<Plugin>,
+    config: BotConfig,
 }
 
 impl Bot {
+    pub fn new(adapter: Box<dyn Adapter>) -> Self {
+        Bot {
+            adapter,
+            plugins: vec![Plugin::new(BUILTIN_PLUGIN, "直接注册在 Bot 上的插件")],
+            config: BotConfig::default(),
+        }
+    }
+
+    /// 使用正向 WebSocket 连接到 OneBot 实现
     pub async fn connect(address: &str) -> Result<Self> {
-        Ok(Bot {
-            adapter: adapter::WsAdapter::connect(address).await?,
-            plugins: vec![Plugin::new("内建插件", "直接注册在 Bot 上的插件")],
-        })
+        Self::connect_with_options(address, ConnectOptions::default()).await
+    }
+
+    pub async fn connect_with_options(address: &str, options: ConnectOptions) -> Result<Self> {
+        Ok(Self::new(
+            adapter::WsAdapter::connect_with_options(address, options).await?,
+        ))
+    }
+
+    /// 使用反向 WebSocket 监听指定地址，等待 OneBot 实现连接
+    pub async fn listen(address: &str) -> Result<Self> {
+        Self::listen_with_options(address, ConnectOptions::default()).await
+    }
+
+    pub async fn listen_with_options(address: &str, options: ConnectOptions) -> Result<Self> {
+        Ok(Self::new(
+            adapter::ReverseWsAdapter::bind_with_options(address, options).await?,
+        ))
+    }
+
+    /// 使用 HTTP API 调用 OneBot 实现，并监听 `listen_address` 接收 HTTP POST 上报的事件
+    ///
+    /// 配置了 secret 时校验上报请求的 `X-Signature`，需要更多选项时使用 `connect_http_with_options`
+    pub async fn connect_http(api_address: &str, listen_address: &str, secret: Option<String>) -> Result<Self> {
+        let options = ConnectOptions {
+            secret,
+            ..Default::default()
+        };
+        Self::connect_http_with_options(api_address, listen_address, options).await
+    }
+
+    pub async fn connect_http_with_options(
+        api_address: &str,
+        listen_address: &str,
+        options: ConnectOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            adapter::HttpAdapter::bind_with_options(api_address, listen_address, options).await?,
+        ))
     }
 
     pub fn on<D, M, H, Fut>(&mut self, description: D, priority: i32, matcher: M, handler: H)
@@ -32,12 +127,63 @@ impl Bot {
         self.plugins[0].on(description, priority, matcher, handler);
     }
 
+    /// 见 `Plugin::on_extract`
+    pub fn on_extract<D, M, H, Args>(&mut self, description: D, priority: i32, matcher: M, handler: H)
+    where
+        D: Into<Cow<'static, str>>,
+        M: Into<Matcher>,
+        H: IntoHandler<Args>,
+    {
+        self.plugins[0].on_extract(description, priority, matcher, handler);
+    }
+
+    pub fn set_nicknames(&mut self, nicknames: impl IntoIterator<Item = impl Into<String>>) {
+        self.config.nicknames = nicknames.into_iter().map(Into::into).collect();
+    }
+
+    pub fn permissions(&self) -> &Permissions {
+        &self.config.permissions
+    }
+
+    /// 从文件中读取插件的启用状态，之后通过 `#plugin` 命令做出的修改会写回该文件
+    pub fn load_plugin_switches(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        self.config.switches = Arc::new(PluginSwitches::load(path)?);
+        Ok(())
+    }
+
+    /// 注册共享状态，处理器可以通过 `State<T>` 参数获取，同一类型重复注册时覆盖之前的值
+    pub fn add_state<T: Send + Sync + 'static>(&mut self, state: T) {
+        self.config.state.insert(Arc::new(state));
+    }
+
+    pub fn register_middleware(&mut self, middleware: impl Middleware + 'static) {
+        self.config.middlewares.push(Arc::new(middleware));
+    }
+
     pub fn register_plugin(&mut self, plugin: Plugin) {
         self.plugins.push(plugin);
     }
 
+    /// 启动 bot，收到 SIGINT 或 SIGTERM 后停止，见 `start_with_shutdown`
+    ///
+    /// 停止过程中再次收到信号时不再等待，直接退出进程
     pub async fn start(self) -> Result<()> {
-        self.adapter.spawn(self.plugins).await
+        self.start_with_shutdown(async {
+            shutdown_signal().await;
+            info!("Received shutdown signal, stopping bot");
+            tokio::spawn(async {
+                shutdown_signal().await;
+                warn!("Received shutdown signal again, exiting immediately");
+                std::process::exit(1);
+            });
+        })
+        .await
+    }
+
+    /// 启动 bot，signal 完成后停止接收新的事件，等待正在执行的处理器与定时任务结束（最多 10 秒），
+    /// 调用插件的停止钩子（最多 10 秒）并关闭连接后返回
+    pub async fn start_with_shutdown(self, signal: impl Future<Output = ()> + Send + 'static) -> Result<()> {
+        self.adapter.spawn(self.plugins, self.config, Box::pin(signal)).await
     }
 
     pub fn use_builtin_handler(&mut self) {
@@ -48,32 +194,74 @@ impl Bot {
             |ctx| async move {
                 let mut help_message = String::from("由 Rust 与 Tokio 驱动的机器人波奇酱！目前由如下插件提供服务：\n");
                 let mut tab_str = 2;
+                let scope = Scope::from_event(&ctx.event);
                 for plugin in ctx.plugins.as_ref() {
+                    let disabled = scope.is_some_and(|scope| !ctx.plugin_switches().is_enabled(scope, &plugin.name));
                     help_message.push_str(&format!(
-                        "\n{}{} - {}\n",
+                        "\n{}{}{} - {}\n",
                         " ".repeat(tab_str),
                         plugin.name,
+                        if disabled { "（已禁用）" } else { "" },
                         plugin.description
                     ));
                     tab_str += 2;
                     for mu in plugin.match_unions() {
                         help_message.push_str(&format!("{}{} - {}\n", " ".repeat(tab_str), mu.matcher, mu.description));
                     }
+                    for job in plugin.jobs() {
+                        help_message.push_str(&format!(
+                            "{}{} - {}\n",
+                            " ".repeat(tab_str),
+                            job.trigger,
+                            job.description
+                        ));
+                    }
                     tab_str -= 2;
                 }
-                ctx.caller
-                    .send_forward_msg(SendForwardMsgParams {
-                        user_id: ctx.event.try_private_user_id().ok(),
-                        group_id: ctx.event.try_group_id().ok(),
-                        messages: MessageContent::Segment(vec![MessageSegment::Node {
-                            id: None,
-                            user_id: ctx.event.try_user_id().ok().map(|id| id.to_string()),
-                            nickname: ctx.event.sender().nickname.clone(),
-                            content: Some(MessageContent::Text(help_message)),
-                        }]),
-                        message_type: None,
-                    })
-                    .await?;
+                ctx.send_forward_segment(vec![MessageSegment::Node {
+                    id: None,
+                    user_id: ctx.event.try_user_id().ok().map(|id| id.to_string()),
+                    nickname: ctx.event.sender().nickname.clone(),
+                    content: Some(MessageContent::Text(help_message)),
+                }])
+                .await?;
+                Ok(true)
+            },
+        );
+        self.on(
+            "在当前群组或私聊中启用／禁用插件",
+            i32::MAX,
+            Rule::on_message()
+                & Rule::on_command("plugin", &[])
+                // 私聊中没有群身份，只允许超级用户使用
+                & (Rule::is_superuser() | (Rule::on_group_message() & Rule::role(Role::Admin))),
+            |ctx| async move {
+                let (action, name) = match ctx.args() {
+                    [action, name @ ..] if !name.is_empty() => (action.as_str(), name.join(" ")),
+                    _ => {
+                        ctx.reply("用法：#plugin enable|disable <插件名>").await?;
+                        return Ok(true);
+                    }
+                };
+                let enabled = match action {
+                    "enable" => true,
+                    "disable" => false,
+                    _ => {
+                        ctx.reply(format!("未知操作：{action}，可用操作为 enable 与 disable"))
+                            .await?;
+                        return Ok(true);
+                    }
+                };
+                let reply = if name == BUILTIN_PLUGIN {
+                    format!("{BUILTIN_PLUGIN}不允许被禁用")
+                } else if !ctx.plugins.iter().any(|plugin| plugin.name == name) {
+                    format!("插件 {name} 不存在")
+                } else {
+                    let scope = Scope::from_event(&ctx.event).ok_or_else(|| anyhow!("无法确定插件开关的作用范围"))?;
+                    ctx.plugin_switches().set_enabled(scope, &name, enabled).await?;
+                    format!("插件 {name} 已{}", if enabled { "启用" } else { "禁用" })
+                };
+                ctx.reply(reply).await?;
                 Ok(true)
             },
         );
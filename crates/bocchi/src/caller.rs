@@ -2,124 +2,206 @@ use anyhow::Result;
 
 use crate::{adapter::Caller, error::ApiError, schema::*};
 
-pub async fn get_login_info(connector: &dyn Caller) -> Result<GetLoginInfoResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::GetLoginInfo))
-        .await?
-        .data
-        .into_get_login_info()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn send_private_msg(connector: &dyn Caller, param: SendPrivateMsgParams) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendPrivateMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn send_group_msg(connector: &dyn Caller, param: SendGroupMsgParams) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendGroupMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn send_msg(connector: &dyn Caller, param: SendMsgParams) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn delete_msg(connector: &dyn Caller, param: DeleteMsgParams) -> Result<serde_json::Value> {
-    connector
-        .call(ApiRequest::new(RequestParams::DeleteMsg(param)))
-        .await?
-        .data
-        .into_fallback()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn get_msg(connector: &dyn Caller, param: GetMsgParams) -> Result<GetMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::GetMsg(param)))
-        .await?
-        .data
-        .into_get_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
-}
-
-pub async fn get_forward_msg(connector: &dyn Caller, param: GetForwardMsgParams) -> Result<GetForwardMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::GetForwardMsg(param)))
-        .await?
-        .data
-        .into_get_forward_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+/// 调用任意 API，响应数据按照请求参数对应的类型解析
+pub async fn call<A: Action>(connector: &(impl Caller + ?Sized), params: A) -> Result<A::Output> {
+    let data = connector.call_raw(ApiRequest::new(params)).await?.into_data()?;
+    Ok(serde_json::from_value(data).map_err(ApiError::ResponseTypeError)?)
+}
+
+pub async fn get_login_info(connector: &(impl Caller + ?Sized)) -> Result<GetLoginInfoResult> {
+    call(connector, GetLoginInfoParams {}).await
+}
+
+pub async fn send_private_msg(
+    connector: &(impl Caller + ?Sized),
+    param: SendPrivateMsgParams,
+) -> Result<SendMsgResult> {
+    call(connector, param).await
+}
+
+pub async fn send_group_msg(connector: &(impl Caller + ?Sized), param: SendGroupMsgParams) -> Result<SendMsgResult> {
+    call(connector, param).await
+}
+
+pub async fn send_msg(connector: &(impl Caller + ?Sized), param: SendMsgParams) -> Result<SendMsgResult> {
+    call(connector, param).await
+}
+
+pub async fn delete_msg(connector: &(impl Caller + ?Sized), param: DeleteMsgParams) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn get_msg(connector: &(impl Caller + ?Sized), param: GetMsgParams) -> Result<GetMsgResult> {
+    call(connector, param).await
+}
+
+pub async fn get_forward_msg(
+    connector: &(impl Caller + ?Sized),
+    param: GetForwardMsgParams,
+) -> Result<GetForwardMsgResult> {
+    call(connector, param).await
+}
+
+pub async fn get_group_list(connector: &(impl Caller + ?Sized)) -> Result<Vec<GroupInfo>> {
+    call(connector, GetGroupListParams {}).await
+}
+
+pub async fn get_group_info(connector: &(impl Caller + ?Sized), param: GetGroupInfoParams) -> Result<GroupInfo> {
+    call(connector, param).await
+}
+
+pub async fn get_group_member_info(
+    connector: &(impl Caller + ?Sized),
+    param: GetGroupMemberInfoParams,
+) -> Result<GroupMemberInfo> {
+    call(connector, param).await
+}
+
+pub async fn get_group_member_list(
+    connector: &(impl Caller + ?Sized),
+    param: GetGroupMemberListParams,
+) -> Result<Vec<GroupMemberInfo>> {
+    call(connector, param).await
+}
+
+pub async fn get_friend_list(connector: &(impl Caller + ?Sized)) -> Result<Vec<FriendInfo>> {
+    call(connector, GetFriendListParams {}).await
+}
+
+pub async fn get_stranger_info(
+    connector: &(impl Caller + ?Sized),
+    param: GetStrangerInfoParams,
+) -> Result<StrangerInfo> {
+    call(connector, param).await
+}
+
+pub async fn get_status(connector: &(impl Caller + ?Sized)) -> Result<StatusResult> {
+    call(connector, GetStatusParams {}).await
+}
+
+pub async fn get_version_info(connector: &(impl Caller + ?Sized)) -> Result<VersionInfoResult> {
+    call(connector, GetVersionInfoParams {}).await
+}
+
+pub async fn set_group_kick(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupKickParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_ban(connector: &(impl Caller + ?Sized), param: SetGroupBanParams) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_anonymous_ban(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupAnonymousBanParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_whole_ban(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupWholeBanParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_admin(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupAdminParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_card(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupCardParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_name(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupNameParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_leave(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupLeaveParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_special_title(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupSpecialTitleParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_friend_add_request(
+    connector: &(impl Caller + ?Sized),
+    param: SetFriendAddRequestParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
+}
+
+pub async fn set_group_add_request(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupAddRequestParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
 }
 
 #[cfg(feature = "napcat")]
-pub async fn set_msg_emoji_like(connector: &dyn Caller, param: SetMsgEmojiLikeParams) -> Result<serde_json::Value> {
-    connector
-        .call(ApiRequest::new(RequestParams::SetMsgEmojiLike(param)))
-        .await?
-        .data
-        .into_fallback()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+pub async fn set_msg_emoji_like(
+    connector: &(impl Caller + ?Sized),
+    param: SetMsgEmojiLikeParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
 }
 
 #[cfg(feature = "go-cqhttp")]
-pub async fn set_group_reaction(connector: &dyn Caller, param: SetGroupReactionParams) -> Result<serde_json::Value> {
-    connector
-        .call(ApiRequest::new(RequestParams::SetGroupReaction(param)))
-        .await?
-        .data
-        .into_fallback()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+pub async fn set_group_reaction(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupReactionParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
 }
 
 #[cfg(feature = "lagrange")]
-pub async fn set_group_reaction(connector: &dyn Caller, param: SetGroupReactionParams) -> Result<serde_json::Value> {
-    connector
-        .call(ApiRequest::new(RequestParams::SetGroupReaction(param)))
-        .await?
-        .data
-        .into_fallback()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+pub async fn set_group_reaction(
+    connector: &(impl Caller + ?Sized),
+    param: SetGroupReactionParams,
+) -> Result<serde_json::Value> {
+    call(connector, param).await
 }
 
 #[cfg(feature = "lagrange")]
 pub async fn send_private_forward_msg(
-    connector: &dyn Caller,
+    connector: &(impl Caller + ?Sized),
     param: SendPrivateForwardMsgParams,
 ) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendPrivateForwardMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+    call(connector, param).await
 }
 
 #[cfg(feature = "lagrange")]
-pub async fn send_group_forward_msg(connector: &dyn Caller, param: SendGroupForwardMsgParams) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendGroupForwardMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+pub async fn send_group_forward_msg(
+    connector: &(impl Caller + ?Sized),
+    param: SendGroupForwardMsgParams,
+) -> Result<SendMsgResult> {
+    call(connector, param).await
 }
 
 #[cfg(feature = "lagrange")]
-pub async fn send_forward_msg(connector: &dyn Caller, param: SendForwardMsgParams) -> Result<SendMsgResult> {
+pub async fn send_forward_msg(
+    connector: &(impl Caller + ?Sized),
+    param: SendForwardMsgParams,
+) -> Result<SendMsgResult> {
     match (param.group_id, param.user_id) {
         (Some(group_id), _) => {
             // 群聊转发
@@ -148,11 +230,9 @@ pub async fn send_forward_msg(connector: &dyn Caller, param: SendForwardMsgParam
 }
 
 #[cfg(any(feature = "napcat", feature = "go-cqhttp"))]
-pub async fn send_forward_msg(connector: &dyn Caller, param: SendForwardMsgParams) -> Result<SendMsgResult> {
-    connector
-        .call(ApiRequest::new(RequestParams::SendForwardMsg(param)))
-        .await?
-        .data
-        .into_send_msg()
-        .map_err(|e| ApiError::ResponseTypeError(e).into())
+pub async fn send_forward_msg(
+    connector: &(impl Caller + ?Sized),
+    param: SendForwardMsgParams,
+) -> Result<SendMsgResult> {
+    call(connector, param).await
 }
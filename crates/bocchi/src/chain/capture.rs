@@ -0,0 +1,143 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::chain::limit::Hit;
+
+/// on_regex 与 on_keyword 匹配到的内容，第 0 组为完整的匹配文本
+#[derive(Debug, Clone, Default)]
+pub struct Captures {
+    groups: Vec<Option<String>>,
+    names: HashMap<String, usize>,
+}
+
+impl Captures {
+    pub(crate) fn from_regex(regex: &regex::Regex, captures: &regex::Captures) -> Self {
+        Self {
+            groups: captures.iter().map(|m| m.map(|m| m.as_str().to_owned())).collect(),
+            names: regex
+                .capture_names()
+                .enumerate()
+                .filter_map(|(i, name)| Some((name?.to_owned(), i)))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn from_keyword(keyword: &str) -> Self {
+        Self {
+            groups: vec![Some(keyword.to_owned())],
+            names: HashMap::new(),
+        }
+    }
+
+    /// 获取第 i 组的内容，未参与匹配的分组返回 None
+    pub fn get(&self, i: usize) -> Option<&str> {
+        self.groups.get(i)?.as_deref()
+    }
+
+    /// 获取命名分组 `(?<name>...)` 的内容
+    pub fn name(&self, name: &str) -> Option<&str> {
+        self.get(*self.names.get(name)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/// on_command 匹配到的命令参数
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Args {
+    /// 命令之后的原始文本，已去除首尾空白
+    pub(crate) raw: String,
+    pub(crate) list: Vec<String>,
+}
+
+/// 一次匹配过程中由各个规则提取的内容，匹配成功后交给 Context
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Captured {
+    pub(crate) args: Option<Args>,
+    pub(crate) captures: Option<Captures>,
+    /// 触发了需要回复的频率限制，值为需要等待的时间
+    pub(crate) limited: Option<Duration>,
+    /// 匹配过程中通过检查的频率限制，交给处理器之前提交
+    pub(crate) hits: Vec<Hit>,
+}
+
+impl Captured {
+    /// 使用另一次匹配的结果覆盖当前结果中对应的部分
+    pub(crate) fn merge(&mut self, other: Captured) {
+        if other.args.is_some() {
+            self.args = other.args;
+        }
+        if other.captures.is_some() {
+            self.captures = other.captures;
+        }
+        if other.limited.is_some() {
+            self.limited = other.limited;
+        }
+        self.hits.extend(other.hits);
+    }
+
+    /// 记录所有通过检查的频率限制，其他事件在此期间占满次数时返回需要等待的时间
+    pub(crate) fn commit_hits(&self) -> Result<(), Duration> {
+        self.hits.iter().try_for_each(Hit::commit)
+    }
+}
+
+/// 按照类似 shell 的规则切分参数，支持单引号、双引号与反斜杠转义，未闭合的引号视为在末尾闭合
+pub(crate) fn split_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    // 区分空字符串参数 `""` 与参数之间的空白
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            // 单引号内的内容不做任何转义
+            (Some('\''), c) => current.push(c),
+            (_, '\\') => {
+                in_arg = true;
+                current.extend(chars.next());
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                in_arg = true;
+                quote = Some(c);
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"  a "b c" 'd \e' f\ g "" "h\"i"  "#),
+            vec!["a", "b c", r"d \e", "f g", "", "h\"i"]
+        );
+        assert_eq!(split_args("\"unterminated arg"), vec!["unterminated arg"]);
+        assert!(split_args("   ").is_empty());
+    }
+}
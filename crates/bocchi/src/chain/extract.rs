@@ -0,0 +1,350 @@
+use std::{any::type_name, future::Future, ops::Deref, sync::Arc};
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::{
+    adapter::Caller,
+    chain::{Captures, Context, Handler},
+    schema::{Event, MessageSegment},
+};
+
+/// 从 Context 中提取处理器的参数，提取失败时不会调用处理器，视为该处理器不适用于此事件
+///
+/// 提取可能失败的参数可以使用 `Option<T>`，失败时得到 None
+///
+/// 返回 [`StateNotRegistered`] 时视为编程错误，不会静默跳过，而是作为处理器的错误返回
+pub trait FromContext: Sized {
+    fn from_context(ctx: &Context) -> Result<Self>;
+}
+
+impl FromContext for Context {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(ctx.clone())
+    }
+}
+
+impl FromContext for Arc<Event> {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(ctx.event.clone())
+    }
+}
+
+impl FromContext for Arc<dyn Caller> {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(ctx.caller.clone())
+    }
+}
+
+impl<T: FromContext> FromContext for Option<T> {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(T::from_context(ctx).ok())
+    }
+}
+
+/// 事件发送者的 QQ 号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserId(pub u64);
+
+impl FromContext for UserId {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        ctx.event.try_user_id().map(Self)
+    }
+}
+
+/// 事件所在的群号，私聊消息无法提取
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupId(pub u64);
+
+impl FromContext for GroupId {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        ctx.event.try_group_id().map(Self)
+    }
+}
+
+/// 收到事件的 bot 账号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfId(pub u64);
+
+impl FromContext for SelfId {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self(ctx.event.self_id()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageId(pub i32);
+
+impl FromContext for MessageId {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        ctx.event.try_message_id().map(Self)
+    }
+}
+
+/// 消息中的纯文本内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlainText(pub String);
+
+impl FromContext for PlainText {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self(ctx.event.try_plain_text()?.into_owned()))
+    }
+}
+
+/// 消息的所有消息段
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segments(pub Vec<MessageSegment>);
+
+impl FromContext for Segments {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self(ctx.event.try_message()?.clone().into_segments()))
+    }
+}
+
+/// 消息中所有图片的链接，没有链接的图片使用文件名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Images(pub Vec<String>);
+
+impl FromContext for Images {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        let Segments(segments) = Segments::from_context(ctx)?;
+        Ok(Self(
+            segments
+                .into_iter()
+                .filter_map(|segment| match segment {
+                    MessageSegment::Image { file, url, .. } => Some(url.unwrap_or(file)),
+                    _ => None,
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// 消息中 @ 的用户，不包含 @全体成员
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mentions(pub Vec<u64>);
+
+impl FromContext for Mentions {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        let Segments(segments) = Segments::from_context(ctx)?;
+        Ok(Self(
+            segments
+                .iter()
+                .filter_map(|segment| match segment {
+                    MessageSegment::At { qq } => qq.parse().ok(),
+                    _ => None,
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// on_command 匹配到的命令参数，见 `Context::args`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandArgs(pub Vec<String>);
+
+impl FromContext for CommandArgs {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self(ctx.args().to_vec()))
+    }
+}
+
+/// on_command 匹配到的命令之后的原始文本，见 `Context::raw_args`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawArgs(pub String);
+
+impl FromContext for RawArgs {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(Self(ctx.raw_args().to_owned()))
+    }
+}
+
+impl FromContext for Captures {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        Ok(ctx.captures().clone())
+    }
+}
+
+/// 通过 `Bot::add_state` 注册的共享状态
+#[derive(Debug)]
+pub struct State<T>(pub Arc<T>);
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// 处理器使用了没有通过 `Bot::add_state` 注册的 State
+#[derive(Error, Debug)]
+#[error("State<{0}> is not registered")]
+pub struct StateNotRegistered(pub &'static str);
+
+impl<T: Send + Sync + 'static> FromContext for State<T> {
+    fn from_context(ctx: &Context) -> Result<Self> {
+        ctx.config
+            .state
+            .get::<Arc<T>>()
+            .map(|state| Self(state.clone()))
+            .ok_or_else(|| StateNotRegistered(type_name::<T>()).into())
+    }
+}
+
+/// 处理器的返回值，表示是否继续将事件交给优先级更低的处理器，与返回 bool 等价
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// 等价于返回 true
+    Stop,
+    /// 等价于返回 false
+    Continue,
+}
+
+impl From<Outcome> for bool {
+    fn from(outcome: Outcome) -> Self {
+        outcome == Outcome::Stop
+    }
+}
+
+/// 可以作为处理器的异步函数，每个参数都需要实现 FromContext，返回值为 `Result<bool>` 或 `Result<Outcome>`
+pub trait IntoHandler<Args>: Send + Sync + 'static {
+    fn into_handler(self) -> Handler;
+}
+
+macro_rules! impl_into_handler {
+    ($($arg:ident),*) => {
+        #[allow(non_snake_case, unused_variables, unused_labels)]
+        impl<F, Fut, R, $($arg,)*> IntoHandler<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Result<R>> + Send + 'static,
+            R: Into<bool>,
+            $($arg: FromContext,)*
+        {
+            fn into_handler(self) -> Handler {
+                Box::new(move |ctx| {
+                    // 参数在同步阶段全部提取完成，返回的 Future 不需要持有 Context
+                    let future: Result<Option<Fut>> = 'extract: {
+                        $(
+                            let $arg = match $arg::from_context(&ctx) {
+                                Ok(arg) => arg,
+                                // 未注册的 State 是编程错误，交给处理器的错误处理流程
+                                Err(e) if e.is::<StateNotRegistered>() => break 'extract Err(e),
+                                Err(e) => {
+                                    debug!("Skip handler, failed to extract {}: {e:?}", type_name::<$arg>());
+                                    break 'extract Ok(None);
+                                }
+                            };
+                        )*
+                        Ok(Some(self($($arg),*)))
+                    };
+                    Box::pin(async move {
+                        match future? {
+                            Some(future) => Ok(future.await?.into()),
+                            // 返回 false，事件继续交给优先级更低的处理器
+                            None => Ok(false),
+                        }
+                    })
+                })
+            }
+        }
+    };
+}
+
+impl_into_handler!();
+impl_into_handler!(T1);
+impl_into_handler!(T1, T2);
+impl_into_handler!(T1, T2, T3);
+impl_into_handler!(T1, T2, T3, T4);
+impl_into_handler!(T1, T2, T3, T4, T5);
+impl_into_handler!(T1, T2, T3, T4, T5, T6);
+impl_into_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_into_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::BotConfig,
+        chain::{
+            Matcher, Rule,
+            handler::tests::{context, message},
+        },
+    };
+
+    /// 与 dispatcher 一样先匹配，使 Context 中带有匹配结果
+    async fn matched(matcher: impl Into<Matcher>, event: Event, config: BotConfig) -> Context {
+        let mut ctx = context(event, config).await;
+        let captured = matcher.into().capture(&ctx.event, &ctx.config.permissions).unwrap();
+        ctx.captured = Arc::new(captured);
+        ctx
+    }
+
+    #[tokio::test]
+    async fn test_extract() {
+        let ctx = matched(
+            Rule::on_command("echo", &[]),
+            message(
+                Some(1),
+                2,
+                "#echo a 'b c'[CQ:at,qq=3][CQ:image,file=x.png,url=https://example.com/x.png]",
+            ),
+            BotConfig::default(),
+        )
+        .await;
+        assert_eq!(UserId::from_context(&ctx).unwrap(), UserId(2));
+        assert_eq!(GroupId::from_context(&ctx).unwrap(), GroupId(1));
+        assert_eq!(SelfId::from_context(&ctx).unwrap(), SelfId(10000));
+        assert_eq!(MessageId::from_context(&ctx).unwrap(), MessageId(1));
+        assert_eq!(CommandArgs::from_context(&ctx).unwrap().0, ["a", "b c"]);
+        assert_eq!(RawArgs::from_context(&ctx).unwrap().0, "a 'b c'");
+        assert_eq!(Mentions::from_context(&ctx).unwrap().0, [3]);
+        assert_eq!(Images::from_context(&ctx).unwrap().0, ["https://example.com/x.png"]);
+        assert_eq!(Segments::from_context(&ctx).unwrap().0.len(), 3);
+        // 私聊消息无法提取 GroupId，包装为 Option 时得到 None
+        let ctx = matched(Matcher::new(), message(None, 2, "hello"), BotConfig::default()).await;
+        assert!(GroupId::from_context(&ctx).is_err());
+        assert_eq!(Option::<GroupId>::from_context(&ctx).unwrap(), None);
+        assert_eq!(PlainText::from_context(&ctx).unwrap().0, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_state() {
+        let mut config = BotConfig::default();
+        config.state.insert(Arc::new(42u32));
+        let ctx = matched(Matcher::new(), message(None, 2, "hello"), config).await;
+        assert_eq!(*State::<u32>::from_context(&ctx).unwrap(), 42);
+        assert!(State::<String>::from_context(&ctx).is_err());
+        assert!(Option::<State<String>>::from_context(&ctx).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_into_handler() {
+        let ctx = matched(Matcher::new(), message(None, 2, "hello"), BotConfig::default()).await;
+        let handler = (|| async { Ok(true) }).into_handler();
+        assert!(handler(ctx.clone()).await.unwrap());
+        let handler = (|UserId(id): UserId, PlainText(text): PlainText| async move {
+            assert_eq!((id, text.as_str()), (2, "hello"));
+            Ok(Outcome::Continue)
+        })
+        .into_handler();
+        assert!(!handler(ctx.clone()).await.unwrap());
+        // 提取失败时不调用处理器，并返回 false
+        let handler = (|_: GroupId| async move { Ok(true) }).into_handler();
+        assert!(!handler(ctx.clone()).await.unwrap());
+        // 未注册的 State 不会被静默跳过
+        let handler = (|_: State<String>| async move { Ok(true) }).into_handler();
+        let err = handler(ctx.clone()).await.unwrap_err();
+        assert!(err.is::<StateNotRegistered>(), "{err}");
+        // 处理器返回的错误原样传递
+        let handler = (|_: Context| async move { Err::<bool, _>(anyhow::anyhow!("failed")) }).into_handler();
+        assert!(handler(ctx).await.is_err());
+    }
+}
@@ -1,62 +1,152 @@
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
 
-use anyhow::{Result, ensure};
+use anyhow::{Result, anyhow, bail, ensure};
+use http::Extensions;
+use tokio::sync::oneshot;
 
 use crate::{
     adapter::Caller,
+    bot::BotConfig,
+    chain::{Captured, Captures, Matcher, Sessions},
+    media::MediaStore,
+    permission::Permissions,
     plugin::Plugin,
-    schema::{Event, MessageContent, MessageSegment, SendForwardMsgParams, SendMsgParams, SendMsgResult},
+    schema::{
+        Event, FriendRequest, GroupMessage, GroupRequest, MessageBuilder, MessageContent, MessageSegment,
+        QuickOperation, SendForwardMsgParams, SendMsgParams, SendMsgResult, SetFriendAddRequestParams,
+        SetGroupAddRequestParams, SetGroupAnonymousBanParams, SetGroupBanParams, SetGroupKickParams,
+    },
+    switch::PluginSwitches,
 };
 
+pub(crate) type QuickOperationSender = Arc<Mutex<Option<oneshot::Sender<QuickOperation>>>>;
+
 #[derive(Clone)]
 pub struct Context {
     pub caller: Arc<dyn Caller>,
     pub event: Arc<Event>,
     pub plugins: Arc<Vec<Plugin>>,
+    /// 中间件写入的附加数据，在同一事件的所有处理器之间共享
+    pub extensions: Extensions,
+    pub(crate) quick_operation: Option<QuickOperationSender>,
+    pub(crate) media: Arc<MediaStore>,
+    pub(crate) captured: Arc<Captured>,
+    pub(crate) config: Arc<BotConfig>,
+    pub(crate) sessions: Arc<Sessions>,
 }
 
+/// prompt 等待回复的时间
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
+
 impl Context {
+    /// 超级用户与插件的访问控制列表，修改后对之后的事件立即生效
+    pub fn permissions(&self) -> &Permissions {
+        &self.config.permissions
+    }
+
+    /// 各个群组与私聊中插件的启用状态
+    pub fn plugin_switches(&self) -> &PluginSwitches {
+        &self.config.switches
+    }
+
+    /// on_command 匹配到的命令参数，按照 shell 的规则切分
+    pub fn args(&self) -> &[String] {
+        self.captured.args.as_ref().map_or(&[], |args| &args.list)
+    }
+
+    /// on_command 匹配到的命令之后的原始文本
+    pub fn raw_args(&self) -> &str {
+        self.captured.args.as_ref().map_or("", |args| &args.raw)
+    }
+
+    /// on_regex 或 on_keyword 匹配到的内容，未使用这些规则时为空
+    pub fn captures(&self) -> &Captures {
+        static EMPTY: LazyLock<Captures> = LazyLock::new(Captures::default);
+        self.captured.captures.as_ref().unwrap_or(&EMPTY)
+    }
+
+    /// 等待同一用户在同一群组（或私聊）中发送满足 matcher 的下一条消息，超时返回 None
+    ///
+    /// 等待到的消息不会再分发给其他插件，返回的 Context 对应该消息，可以通过它回复或获取参数
+    pub async fn wait_for(&self, matcher: impl Into<Matcher>, timeout: Duration) -> Result<Option<Context>> {
+        let receiver = self.sessions.wait(
+            self.event.try_group_id().ok(),
+            self.event.try_user_id()?,
+            matcher.into(),
+        );
+        // 超时后 receiver 被丢弃，会话随之失效
+        Ok(tokio::time::timeout(timeout, receiver).await.ok().and_then(Result::ok))
+    }
+
+    /// 发送提问并等待同一用户的任意一条回复，超时返回 None
+    pub async fn prompt(&self, message: impl Into<String>) -> Result<Option<Context>> {
+        self.send(message).await?;
+        self.wait_for(Matcher::default(), PROMPT_TIMEOUT).await
+    }
+
     pub async fn send(&self, message: impl Into<String>) -> Result<SendMsgResult> {
-        self.caller
-            .send_msg(SendMsgParams {
-                user_id: self.event.try_private_user_id().ok(),
-                group_id: self.event.try_group_id().ok(),
-                message: MessageContent::Text(message.into()),
-                auto_escape: true,
-                message_type: None,
-            })
-            .await
+        self.send_msg(MessageContent::Text(message.into())).await
     }
 
-    pub async fn send_content(&self, message: Vec<MessageSegment>) -> Result<SendMsgResult> {
-        self.caller
+    /// 发送消息段，可以直接传入 MessageBuilder，其中的媒体内容会按照 MediaTransport 上传
+    pub async fn send_content(&self, message: impl Into<MessageBuilder>) -> Result<SendMsgResult> {
+        let (segments, media) = message.into().into_parts();
+        // guards 需要存活到消息发送完成，OneBot 实现可能在此之前读取临时文件或链接
+        let (message, _guards) = self.media.resolve(segments, media).await?;
+        self.send_msg(message).await
+    }
+
+    async fn send_msg(&self, message: MessageContent) -> Result<SendMsgResult> {
+        let sent = self.copy_for_after_send(&message);
+        let result = self
+            .caller
             .send_msg(SendMsgParams {
                 user_id: self.event.try_private_user_id().ok(),
                 group_id: self.event.try_group_id().ok(),
-                message: MessageContent::Segment(message),
+                message,
                 auto_escape: true,
                 message_type: None,
             })
-            .await
+            .await;
+        self.after_send(sent, &result).await;
+        result
+    }
+
+    /// 消息可能内联了 base64 媒体，只有注册了中间件时才复制一份交给 after_send
+    fn copy_for_after_send(&self, message: &MessageContent) -> Option<MessageContent> {
+        (!self.config.middlewares.is_empty()).then(|| message.clone())
     }
 
+    async fn after_send(&self, message: Option<MessageContent>, result: &Result<SendMsgResult>) {
+        let Some(message) = message else {
+            return;
+        };
+        for middleware in &self.config.middlewares {
+            middleware.after_send(self, &message, result).await;
+        }
+    }
+
+    /// 回复触发事件的消息，非消息事件没有可以回复的消息，返回 Err
     pub async fn reply(&self, message: impl Into<String>) -> Result<SendMsgResult> {
         self.send_content(vec![
             MessageSegment::Reply {
-                id: self.event.message_id().to_string(),
+                id: self.event.try_message_id()?.to_string(),
             },
             MessageSegment::Text { text: message.into() },
         ])
         .await
     }
 
-    pub async fn reply_content(&self, message: Vec<MessageSegment>) -> Result<SendMsgResult> {
+    pub async fn reply_content(&self, message: impl Into<MessageBuilder>) -> Result<SendMsgResult> {
         self.send_content(
-            std::iter::once(MessageSegment::Reply {
-                id: self.event.message_id().to_string(),
-            })
-            .chain(message)
-            .collect(),
+            MessageBuilder::new()
+                .reply(self.event.try_message_id()?)
+                .append(message),
         )
         .await
     }
@@ -93,42 +183,216 @@ impl Context {
         unreachable!("Unsupported")
     }
 
-    pub async fn send_forward(&self, messages: Vec<String>) -> Result<SendMsgResult> {
-        self.send_forward_content(messages.into_iter().map(MessageContent::Text).collect())
+    /// 禁言发送者，duration 为零时取消禁言，发送者为匿名用户时无法取消禁言，此时返回错误
+    pub async fn ban_sender(&self, duration: Duration) -> Result<serde_json::Value> {
+        let group_id = self.event.try_group_id()?;
+        if let Event::GroupMessage(GroupMessage {
+            anonymous: Some(anonymous),
+            ..
+        }) = self.event.as_ref()
+        {
+            // 匿名禁言不支持取消，时长为零时 OneBot 实现的行为不确定，直接返回错误
+            ensure!(
+                duration.as_secs() > 0,
+                "Cannot lift the ban of an anonymous sender, duration must be at least one second"
+            );
+            return self
+                .caller
+                .set_group_anonymous_ban(SetGroupAnonymousBanParams {
+                    group_id,
+                    anonymous_flag: anonymous.flag.clone(),
+                    duration: duration.as_secs(),
+                })
+                .await;
+        }
+        self.caller
+            .set_group_ban(SetGroupBanParams {
+                group_id,
+                user_id: self.event.try_user_id()?,
+                duration: duration.as_secs(),
+            })
+            .await
+    }
+
+    /// 将发送者踢出群组，reject_add_request 为 true 时拒绝其再次加群
+    pub async fn kick_sender(&self, reject_add_request: bool) -> Result<serde_json::Value> {
+        self.caller
+            .set_group_kick(SetGroupKickParams {
+                group_id: self.event.try_group_id()?,
+                user_id: self.event.try_user_id()?,
+                reject_add_request,
+            })
             .await
     }
 
-    pub async fn send_forward_content(&self, messages: Vec<MessageContent>) -> Result<SendMsgResult> {
-        let user_id = self.event.try_user_id().ok().map(|id| id.to_string());
-        let nickname = self.event.sender().nickname.clone();
+    /// 同意加好友请求或加群请求／邀请
+    pub async fn approve(&self) -> Result<serde_json::Value> {
+        self.handle_request(true, String::new()).await
+    }
+
+    /// 拒绝加好友请求或加群请求／邀请，拒绝理由仅对加群请求有效
+    pub async fn reject(&self, reason: impl Into<String>) -> Result<serde_json::Value> {
+        self.handle_request(false, reason.into()).await
+    }
+
+    async fn handle_request(&self, approve: bool, reason: String) -> Result<serde_json::Value> {
+        match self.event.as_ref() {
+            Event::FriendRequest(FriendRequest { flag, .. }) => {
+                self.caller
+                    .set_friend_add_request(SetFriendAddRequestParams {
+                        flag: flag.clone(),
+                        approve,
+                        remark: String::new(),
+                    })
+                    .await
+            }
+            Event::GroupRequest(GroupRequest { flag, sub_type, .. }) => {
+                self.caller
+                    .set_group_add_request(SetGroupAddRequestParams {
+                        flag: flag.clone(),
+                        sub_type: sub_type.clone(),
+                        approve,
+                        reason,
+                    })
+                    .await
+            }
+            _ => bail!("Context::approve() and Context::reject() called on non-request event"),
+        }
+    }
+
+    /// 设置快速操作，仅在事件通过 HTTP POST 上报时可用，每个事件只能设置一次
+    ///
+    /// 事件的处理流程结束后上报请求会立即得到响应，需要在处理器返回前设置
+    pub fn quick_operation(&self, operation: QuickOperation) -> Result<()> {
+        let sender = self
+            .quick_operation
+            .as_ref()
+            .ok_or_else(|| anyhow!("Quick operation is only available for events reported by HTTP POST"))?
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| anyhow!("Quick operation has already been set or expired"))?;
+        // 上报请求已经超时响应时，接收端会被丢弃
+        sender.send(operation).map_err(|_| anyhow!("Quick operation expired"))
+    }
+
+    pub async fn send_forward(&self, messages: Vec<String>) -> Result<SendMsgResult> {
+        self.send_forward_content(messages).await
+    }
+
+    /// 以发送者的身份将每条消息作为一个节点发送合并转发消息
+    pub async fn send_forward_content(&self, messages: Vec<impl Into<MessageBuilder>>) -> Result<SendMsgResult> {
+        let user_id = self.event.try_user_id().unwrap_or_default();
+        let nickname = self
+            .event
+            .try_sender()
+            .ok()
+            .and_then(|sender| sender.nickname.clone())
+            .unwrap_or_default();
         self.send_forward_segment(
             messages
                 .into_iter()
-                .map(|m| MessageSegment::Node {
-                    id: None,
-                    user_id: user_id.clone(),
-                    nickname: nickname.clone(),
-                    content: Some(m),
-                })
-                .collect(),
+                .fold(MessageBuilder::new(), |builder, m| builder.node(user_id, &nickname, m)),
         )
         .await
     }
 
-    pub async fn send_forward_segment(&self, messages: Vec<MessageSegment>) -> Result<SendMsgResult> {
+    /// 发送合并转发消息，所有消息段都需要是 Node 类型
+    pub async fn send_forward_segment(&self, messages: impl Into<MessageBuilder>) -> Result<SendMsgResult> {
+        let (segments, media) = messages.into().into_parts();
         ensure!(
-            messages.iter().all(|m| matches!(m, MessageSegment::Node { .. })),
+            segments.iter().all(|m| matches!(m, MessageSegment::Node { .. })),
             "All segments must be of type Node"
         );
-        self.caller
+        let (messages, _guards) = self.media.resolve(segments, media).await?;
+        let sent = self.copy_for_after_send(&messages);
+        let result = self
+            .caller
             .send_forward_msg(SendForwardMsgParams {
                 user_id: self.event.try_private_user_id().ok(),
                 group_id: self.event.try_group_id().ok(),
-                messages: MessageContent::Segment(messages),
+                messages,
                 message_type: None,
             })
-            .await
+            .await;
+        self.after_send(sent, &result).await;
+        result
     }
 }
 
 pub type Handler = Box<dyn Fn(Context) -> Pin<Box<dyn Future<Output = Result<bool>> + Send>> + Send + Sync>;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        media::MediaTransport,
+        schema::{ApiRequest, ApiResponse},
+    };
+
+    /// 不连接任何 OneBot 实现，调用 API 时总是返回错误
+    pub(crate) struct NoopCaller;
+
+    #[async_trait]
+    impl Caller for NoopCaller {
+        async fn call_raw(&self, _: ApiRequest) -> Result<ApiResponse> {
+            bail!("Not connected")
+        }
+    }
+
+    /// group_id 为 None 时是私聊消息，否则是群消息
+    pub(crate) fn message(group_id: Option<u64>, user_id: u64, text: &str) -> Event {
+        let mut event = serde_json::json!({
+            "time": 1700000000,
+            "self_id": 10000,
+            "post_type": "message",
+            "message_type": "private",
+            "sub_type": "friend",
+            "message_id": 1,
+            "user_id": user_id,
+            "message": text,
+            "raw_message": text,
+            "font": 0,
+            "sender": { "user_id": user_id, "nickname": "kita", "role": "member" },
+        });
+        if let Some(group_id) = group_id {
+            event["message_type"] = "group".into();
+            event["sub_type"] = "normal".into();
+            event["group_id"] = group_id.into();
+        }
+        let mut event: Event = serde_json::from_value(event).unwrap();
+        event.normalize();
+        event
+    }
+
+    pub(crate) async fn context(event: Event, config: BotConfig) -> Context {
+        Context {
+            caller: Arc::new(NoopCaller),
+            event: Arc::new(event),
+            plugins: Arc::default(),
+            extensions: Extensions::default(),
+            quick_operation: None,
+            media: Arc::new(MediaStore::new(MediaTransport::Base64).await.unwrap()),
+            captured: Arc::default(),
+            config: Arc::new(config),
+            sessions: Arc::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reply_on_notice() {
+        let event: Event = serde_json::from_str(
+            r#"{"time":1700000000,"self_id":10000,"post_type":"notice","notice_type":"group_increase","sub_type":"approve","group_id":1,"operator_id":3,"user_id":2}"#,
+        )
+        .unwrap();
+        let ctx = context(event, BotConfig::default()).await;
+        // 通知没有可以回复的消息，也没有发送者，不应 panic
+        let err = ctx.reply("welcome").await.unwrap_err();
+        assert!(err.to_string().contains("try_message_id"), "{err}");
+        assert!(ctx.reply_content("welcome").await.is_err());
+        let err = ctx.send_forward(vec!["welcome".to_string()]).await.unwrap_err();
+        assert_eq!(err.to_string(), "Not connected");
+    }
+}
@@ -0,0 +1,201 @@
+//! cooldown 与 rate_limit 使用的频率限制，按照 GCRA 算法实现
+//!
+//! 没有使用 bocchi_bot 中已有的 governor：governor 检查时会立即消耗一次额度，
+//! 而这里需要先检查、等到整个 Matcher 匹配成功后再记录（见 `Hit::commit`），
+//! 否则没有匹配的事件也会占用次数
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+
+use crate::schema::Event;
+
+/// 超过该数量后，检查时会顺便清理已经恢复的记录
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// 频率限制的统计范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitScope {
+    /// 每个用户分别计算
+    User,
+    /// 每个群组分别计算，私聊按照用户计算
+    Group,
+    /// 所有事件共用
+    Global,
+}
+
+/// 每 period 内最多允许 max 次，允许短时间内连续触发 max 次
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub max: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub fn new(max: u32, period: Duration) -> Self {
+        Self { max, period }
+    }
+
+    pub fn per_minute(max: u32) -> Self {
+        Self::new(max, Duration::from_secs(60))
+    }
+
+    /// 两次之间至少间隔 interval
+    pub fn cooldown(interval: Duration) -> Self {
+        Self::new(1, interval)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LimitKey {
+    User(u64),
+    Group(u64),
+    Private(u64),
+    Global,
+}
+
+/// 基于 GCRA 的频率限制器，每个键只需要记录一个时间点
+#[derive(Debug, Clone)]
+pub(crate) struct Limiter {
+    scope: LimitScope,
+    /// 每次触发占用的时间
+    interval: Duration,
+    /// 允许提前占用的时间
+    tolerance: Duration,
+    /// 每个键的理论到达时间，早于当前时间时视为已经完全恢复
+    states: Arc<DashMap<LimitKey, Instant>>,
+}
+
+/// 通过检查但尚未记录的触发，整个 Matcher 匹配成功后再提交，避免未生效的匹配占用次数
+#[derive(Debug, Clone)]
+pub(crate) struct Hit {
+    limiter: Limiter,
+    key: LimitKey,
+}
+
+impl Hit {
+    /// 记录本次触发，期间其他事件已经占满次数时返回需要等待的时间
+    pub(crate) fn commit(&self) -> Result<(), Duration> {
+        self.limiter.acquire(self.key, Instant::now())
+    }
+}
+
+impl Limiter {
+    pub(crate) fn new(scope: LimitScope, quota: Quota) -> Self {
+        let interval = quota.period / quota.max.max(1);
+        Self {
+            scope,
+            interval,
+            tolerance: quota.period.saturating_sub(interval),
+            states: Arc::default(),
+        }
+    }
+
+    /// 检查是否超出限制但不记录，未超出时返回待提交的触发，超出时返回需要等待的时间
+    ///
+    /// 无法确定统计键的事件不受限制，此时返回 None
+    pub(crate) fn check(&self, event: &Event) -> Result<Option<Hit>, Duration> {
+        let Some(key) = self.key(event) else {
+            return Ok(None);
+        };
+        self.wait_time(key, Instant::now())?;
+        Ok(Some(Hit {
+            limiter: self.clone(),
+            key,
+        }))
+    }
+
+    fn wait_time(&self, key: LimitKey, now: Instant) -> Result<(), Duration> {
+        let Some(tat) = self.states.get(&key).map(|tat| *tat) else {
+            return Ok(());
+        };
+        match tat.checked_sub(self.tolerance) {
+            Some(earliest) if earliest > now => Err(earliest - now),
+            _ => Ok(()),
+        }
+    }
+
+    /// 未超出限制时记录一次触发，否则返回需要等待的时间
+    fn acquire(&self, key: LimitKey, now: Instant) -> Result<(), Duration> {
+        if self.states.len() > PRUNE_THRESHOLD {
+            self.states.retain(|_, tat| *tat > now);
+        }
+        let mut tat = self.states.entry(key).or_insert(now);
+        if let Some(earliest) = tat.checked_sub(self.tolerance)
+            && earliest > now
+        {
+            return Err(earliest - now);
+        }
+        *tat = (*tat).max(now) + self.interval;
+        Ok(())
+    }
+
+    fn key(&self, event: &Event) -> Option<LimitKey> {
+        match self.scope {
+            LimitScope::User => event.try_user_id().ok().map(LimitKey::User),
+            LimitScope::Group => match event.try_group_id() {
+                Ok(group_id) => Some(LimitKey::Group(group_id)),
+                Err(_) => event.try_user_id().ok().map(LimitKey::Private),
+            },
+            LimitScope::Global => Some(LimitKey::Global),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_burst() {
+        // 每 10 秒 5 次，每次占用 2 秒
+        let limiter = Limiter::new(LimitScope::Global, Quota::new(5, SECOND * 10));
+        let now = Instant::now();
+        for _ in 0..5 {
+            assert_eq!(limiter.wait_time(LimitKey::Global, now), Ok(()));
+            assert_eq!(limiter.acquire(LimitKey::Global, now), Ok(()));
+        }
+        assert_eq!(limiter.wait_time(LimitKey::Global, now), Err(SECOND * 2));
+        assert_eq!(limiter.acquire(LimitKey::Global, now), Err(SECOND * 2));
+        // 每经过一个 interval 恢复一次
+        assert_eq!(limiter.acquire(LimitKey::Global, now + SECOND * 2), Ok(()));
+        assert_eq!(limiter.acquire(LimitKey::Global, now + SECOND * 3), Err(SECOND));
+        // 完全恢复后可以再次连续触发 max 次
+        let later = now + SECOND * 30;
+        for _ in 0..5 {
+            assert_eq!(limiter.acquire(LimitKey::Global, later), Ok(()));
+        }
+        assert!(limiter.acquire(LimitKey::Global, later).is_err());
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let limiter = Limiter::new(LimitScope::User, Quota::cooldown(SECOND * 5));
+        let now = Instant::now();
+        assert_eq!(limiter.acquire(LimitKey::User(1), now), Ok(()));
+        assert_eq!(limiter.acquire(LimitKey::User(1), now + SECOND), Err(SECOND * 4));
+        // 被拒绝的触发不会延长等待时间
+        assert_eq!(limiter.acquire(LimitKey::User(1), now + SECOND * 2), Err(SECOND * 3));
+        // 不同的键分别计算
+        assert_eq!(limiter.acquire(LimitKey::User(2), now + SECOND), Ok(()));
+        assert_eq!(limiter.acquire(LimitKey::User(1), now + SECOND * 5), Ok(()));
+    }
+
+    #[test]
+    fn test_check_does_not_record() {
+        let limiter = Limiter::new(LimitScope::Global, Quota::cooldown(SECOND * 60));
+        let event: Event = serde_json::from_str(
+            r#"{"time":1700000000,"self_id":10000,"post_type":"notice","notice_type":"friend_add","user_id":2}"#,
+        )
+        .unwrap();
+        let hit = limiter.check(&event).unwrap().unwrap();
+        assert!(limiter.check(&event).is_ok());
+        hit.commit().unwrap();
+        assert!(limiter.check(&event).is_err());
+        assert!(hit.commit().is_err());
+    }
+}
@@ -1,25 +1,68 @@
-use std::{fmt::Display, ops};
+use std::{
+    fmt::{self, Display},
+    ops,
+    sync::LazyLock,
+};
 
 use crate::{
-    chain::{Rule, rule::InnerRule},
+    chain::{Rule, capture::Captured, rule::InnerRule},
+    permission::Permissions,
     schema::Event,
 };
 
-#[derive(Default)]
-pub struct Matcher {
-    pub condition: Vec<Rule>,
+/// 由 Rule 组成的匹配条件，支持通过 `&`、`|` 与 `!` 任意组合
+///
+/// 求值时按照书写顺序短路，开销较大的 Rule 建议放在后面
+pub enum Matcher {
+    Rule(Rule),
+    /// 全部满足时匹配，为空时总是匹配
+    And(Vec<Matcher>),
+    /// 任意一个满足时匹配，为空时总是不匹配
+    Or(Vec<Matcher>),
+    Not(Box<Matcher>),
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::And(Vec::new())
+    }
 }
 
 impl Display for Matcher {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for rule in self.condition.iter().take(self.condition.len() - 1) {
-            write!(f, "{} & ", rule.name)?;
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rule(rule) => write!(f, "{}", rule.name),
+            Self::And(matchers) => write_joined(f, matchers, " & ", |m| matches!(m, Self::Or(m) if m.len() > 1)),
+            Self::Or(matchers) => write_joined(f, matchers, " | ", |m| matches!(m, Self::And(m) if m.len() > 1)),
+            Self::Not(matcher) => {
+                if matcher.is_compound() {
+                    write!(f, "!({matcher})")
+                } else {
+                    write!(f, "!{matcher}")
+                }
+            }
+        }
+    }
+}
+
+/// 按照分隔符拼接子条件，优先级较低的子条件需要加上括号
+fn write_joined(
+    f: &mut fmt::Formatter<'_>,
+    matchers: &[Matcher],
+    separator: &str,
+    need_paren: impl Fn(&Matcher) -> bool,
+) -> fmt::Result {
+    for (i, matcher) in matchers.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
         }
-        if let Some(rule) = self.condition.last() {
-            write!(f, "{}", rule.name)?;
+        if need_paren(matcher) {
+            write!(f, "({matcher})")?;
+        } else {
+            write!(f, "{matcher}")?;
         }
-        Ok(())
     }
+    Ok(())
 }
 
 impl Matcher {
@@ -27,60 +70,269 @@ impl Matcher {
         Self::default()
     }
 
+    /// 追加需要同时满足的规则
     pub fn add(&mut self, rules: Vec<Rule>) {
-        self.condition.extend(rules);
+        let matcher = std::mem::take(self);
+        *self = rules.into_iter().fold(matcher, |matcher, rule| matcher & rule);
     }
 
+    /// 不读取运行时的权限配置，`Rule::is_superuser` 等规则总是视为不满足，需要时使用 `is_match_with`
     pub fn is_match(&self, event: &Event) -> bool {
-        for rule in &self.condition {
-            match &rule.inner {
-                InnerRule::OnText(handler) => {
-                    if !handler(&event.plain_text()) {
-                        return false;
-                    }
-                }
-                InnerRule::OnSender(handler) => {
-                    if !handler(event.sender()) {
-                        return false;
-                    }
-                }
-                InnerRule::OnEventStatic(handler) => {
-                    if !handler(event) {
-                        return false;
-                    }
-                }
-                InnerRule::OnEvent(handler) => {
-                    if !handler(event) {
-                        return false;
-                    }
+        static NO_PERMISSIONS: LazyLock<Permissions> = LazyLock::new(Permissions::default);
+        self.is_match_with(event, &NO_PERMISSIONS)
+    }
+
+    pub fn is_match_with(&self, event: &Event, permissions: &Permissions) -> bool {
+        self.capture(event, permissions).is_some()
+    }
+
+    /// 匹配成功时返回各个规则提取的内容
+    pub(crate) fn capture(&self, event: &Event, permissions: &Permissions) -> Option<Captured> {
+        let mut captured = Captured::default();
+        self.matches(event, permissions, &mut captured).then_some(captured)
+    }
+
+    fn matches(&self, event: &Event, permissions: &Permissions, captured: &mut Captured) -> bool {
+        match self {
+            Self::Rule(rule) => rule.is_match(event, permissions, captured),
+            Self::And(matchers) => matchers.iter().all(|m| m.matches(event, permissions, captured)),
+            // 未匹配的分支可能已经提取了部分内容，只采用匹配成功的分支的结果
+            Self::Or(matchers) => matchers.iter().any(|m| {
+                let mut branch = Captured::default();
+                let matched = m.matches(event, permissions, &mut branch);
+                if matched {
+                    captured.merge(branch);
                 }
+                matched
+            }),
+            Self::Not(matcher) => !matcher.matches(event, permissions, &mut Captured::default()),
+        }
+    }
+
+    /// 条件中是否包含 `Rule::to_me`
+    pub(crate) fn uses_to_me(&self) -> bool {
+        match self {
+            Self::Rule(rule) => matches!(rule.inner, InnerRule::ToMe),
+            Self::And(matchers) | Self::Or(matchers) => matchers.iter().any(Self::uses_to_me),
+            Self::Not(matcher) => matcher.uses_to_me(),
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        matches!(self, Self::And(m) | Self::Or(m) if m.len() > 1)
+    }
+
+    /// 合并同类的子条件，避免 `a & b & c` 产生多层嵌套
+    fn and(self, rhs: Matcher) -> Self {
+        match (self, rhs) {
+            (Self::And(mut lhs), Self::And(rhs)) => {
+                lhs.extend(rhs);
+                Self::And(lhs)
+            }
+            (Self::And(mut lhs), rhs) => {
+                lhs.push(rhs);
+                Self::And(lhs)
+            }
+            (lhs, Self::And(mut rhs)) => {
+                rhs.insert(0, lhs);
+                Self::And(rhs)
+            }
+            (lhs, rhs) => Self::And(vec![lhs, rhs]),
+        }
+    }
+
+    fn or(self, rhs: Matcher) -> Self {
+        match (self, rhs) {
+            (Self::Or(mut lhs), Self::Or(rhs)) => {
+                lhs.extend(rhs);
+                Self::Or(lhs)
+            }
+            (Self::Or(mut lhs), rhs) => {
+                lhs.push(rhs);
+                Self::Or(lhs)
             }
+            (lhs, Self::Or(mut rhs)) => {
+                rhs.insert(0, lhs);
+                Self::Or(rhs)
+            }
+            (lhs, rhs) => Self::Or(vec![lhs, rhs]),
         }
-        true
     }
 }
 
-impl ops::BitAnd<Matcher> for Matcher {
+impl<T: Into<Matcher>> ops::BitAnd<T> for Matcher {
     type Output = Self;
 
-    fn bitand(self, rhs: Matcher) -> Self::Output {
-        Self {
-            condition: self.condition.into_iter().chain(rhs.condition).collect(),
-        }
+    fn bitand(self, rhs: T) -> Self::Output {
+        self.and(rhs.into())
+    }
+}
+
+impl<T: Into<Matcher>> ops::BitOr<T> for Matcher {
+    type Output = Self;
+
+    fn bitor(self, rhs: T) -> Self::Output {
+        self.or(rhs.into())
     }
 }
 
-impl ops::BitAnd<Rule> for Matcher {
+impl ops::Not for Matcher {
     type Output = Self;
 
-    fn bitand(mut self, rhs: Rule) -> Self::Output {
-        self.add(vec![rhs]);
-        self
+    fn not(self) -> Self::Output {
+        match self {
+            Self::Not(matcher) => *matcher,
+            matcher => Self::Not(Box::new(matcher)),
+        }
     }
 }
 
 impl From<Rule> for Matcher {
     fn from(rule: Rule) -> Self {
-        Self { condition: vec![rule] }
+        Self::Rule(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::chain::LimitScope;
+
+    fn rule(name: &'static str, result: bool) -> Rule {
+        counted(name, result, Arc::default())
+    }
+
+    /// 记录被求值次数的规则
+    fn counted(name: &'static str, result: bool, count: Arc<AtomicUsize>) -> Rule {
+        Rule {
+            name: name.into(),
+            inner: InnerRule::OnEvent(Box::new(move |_| {
+                count.fetch_add(1, Ordering::Relaxed);
+                result
+            })),
+        }
+    }
+
+    fn message(text: &str) -> Event {
+        let mut event: Event = serde_json::from_value(serde_json::json!({
+            "time": 1700000000,
+            "self_id": 10000,
+            "post_type": "message",
+            "message_type": "private",
+            "sub_type": "friend",
+            "message_id": 1,
+            "user_id": 20000,
+            "message": text,
+            "raw_message": text,
+            "font": 0,
+            "sender": { "user_id": 20000, "nickname": "bocchi" },
+        }))
+        .unwrap();
+        event.normalize();
+        event
+    }
+
+    #[test]
+    fn test_flatten() {
+        let matcher =
+            (Matcher::from(rule("a", true)) & rule("b", true)) & (Matcher::from(rule("c", true)) & rule("d", true));
+        assert!(matches!(&matcher, Matcher::And(m) if m.len() == 4));
+        let matcher = Matcher::from(rule("a", true)) | (Matcher::from(rule("b", true)) | rule("c", true));
+        assert!(matches!(&matcher, Matcher::Or(m) if m.len() == 3));
+        // 不同种类的条件不会合并
+        let matcher = Matcher::from(rule("a", true)) & (Matcher::from(rule("b", true)) | rule("c", true));
+        assert!(matches!(&matcher, Matcher::And(m) if m.len() == 2));
+        // 双重否定直接抵消
+        assert!(matches!(!!Matcher::from(rule("a", true)), Matcher::Rule(_)));
+        let mut matcher = Matcher::new();
+        matcher.add(vec![rule("a", true), rule("b", true)]);
+        assert!(matches!(&matcher, Matcher::And(m) if m.len() == 2));
+    }
+
+    #[test]
+    fn test_display() {
+        let a = || Matcher::from(rule("a", true));
+        assert_eq!((a() & rule("b", true) & rule("c", true)).to_string(), "a & b & c");
+        assert_eq!((a() & (a() | rule("b", true))).to_string(), "a & (a | b)");
+        assert_eq!((a() | (a() & rule("b", true))).to_string(), "a | (a & b)");
+        assert_eq!((!a()).to_string(), "!a");
+        assert_eq!((!(a() | rule("b", true))).to_string(), "!(a | b)");
+        assert_eq!((!a() & !(a() & rule("b", true))).to_string(), "!a & !(a & b)");
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        let (event, permissions) = (message("hello"), Permissions::default());
+        let count = Arc::new(AtomicUsize::new(0));
+        let matcher = Matcher::from(rule("a", false)) & counted("b", true, count.clone());
+        assert!(matcher.capture(&event, &permissions).is_none());
+        let matcher = Matcher::from(rule("a", true)) | counted("b", true, count.clone());
+        assert!(matcher.capture(&event, &permissions).is_some());
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+        let matcher = Matcher::from(rule("a", false)) | counted("b", true, count.clone());
+        assert!(matcher.capture(&event, &permissions).is_some());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        assert!(Matcher::new().capture(&event, &permissions).is_some());
+        assert!(Matcher::Or(Vec::new()).capture(&event, &permissions).is_none());
+        assert!(
+            (!Matcher::from(rule("a", false)))
+                .capture(&event, &permissions)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_is_match_permissions() {
+        let (event, permissions) = (message("hello"), Permissions::default());
+        let matcher = Matcher::from(Rule::is_superuser());
+        assert!(!matcher.is_match_with(&event, &permissions));
+        permissions.add_superuser(20000);
+        assert!(matcher.is_match_with(&event, &permissions));
+        // is_match 不读取运行时的权限配置
+        assert!(!matcher.is_match(&event));
+        assert!(Matcher::from(Rule::on_message()).is_match(&event));
+    }
+
+    #[test]
+    fn test_limit_hits() {
+        let (event, permissions) = (message("hello"), Permissions::default());
+        // 未匹配的分支中通过检查的频率限制不会被提交
+        let matcher = (Rule::cooldown(LimitScope::User, Duration::from_secs(60)) & rule("a", false)) | rule("b", true);
+        assert!(matcher.capture(&event, &permissions).unwrap().hits.is_empty());
+        // 匹配本身不记录触发，提交之后才生效
+        let matcher = Rule::cooldown(LimitScope::User, Duration::from_secs(60)) & rule("a", true);
+        let captured = matcher.capture(&event, &permissions).unwrap();
+        assert_eq!(captured.hits.len(), 1);
+        assert!(matcher.capture(&event, &permissions).is_some());
+        captured.commit_hits().unwrap();
+        assert!(matcher.capture(&event, &permissions).is_none());
+    }
+
+    #[test]
+    fn test_or_capture() {
+        let permissions = Permissions::default();
+        // 第一个分支提取了命令参数但没有匹配，结果中只保留第二个分支的内容
+        let matcher = (Matcher::from(Rule::on_command("echo", &[])) & rule("a", false)) | Rule::on_keyword(&["hello"]);
+        let captured = matcher.capture(&message("#echo hello"), &permissions).unwrap();
+        assert!(captured.args.is_none());
+        assert_eq!(captured.captures.unwrap().get(0), Some("hello"));
+        // 匹配成功的分支与外层的结果合并
+        let matcher = Matcher::from(Rule::on_command("echo", &[]))
+            & (Matcher::from(rule("a", false)) | Rule::on_keyword(&["hello"]));
+        let captured = matcher.capture(&message("#echo hello world"), &permissions).unwrap();
+        assert_eq!(captured.args.unwrap().list, ["hello", "world"]);
+        assert_eq!(captured.captures.unwrap().get(0), Some("hello"));
+        // Not 内部提取的内容不会保留
+        let matcher = Matcher::from(Rule::on_message()) & !(Rule::on_command("echo", &[]) & rule("a", false));
+        let captured = matcher.capture(&message("#echo hello"), &permissions).unwrap();
+        assert!(captured.args.is_none());
     }
 }
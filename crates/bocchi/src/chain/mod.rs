@@ -1,11 +1,26 @@
+mod capture;
+mod extract;
 mod handler;
+mod limit;
 mod matcher;
 mod rule;
+mod session;
 use std::borrow::Cow;
 
+pub(crate) use capture::Captured;
+pub use capture::Captures;
+pub use extract::{
+    CommandArgs, FromContext, GroupId, Images, IntoHandler, Mentions, MessageId, Outcome, PlainText, RawArgs, Segments,
+    SelfId, State, StateNotRegistered, UserId,
+};
+pub(crate) use handler::QuickOperationSender;
+#[cfg(test)]
+pub(crate) use handler::tests::{NoopCaller, message};
 pub use handler::{Context, Handler};
+pub use limit::{LimitScope, Quota};
 pub use matcher::Matcher;
-pub use rule::Rule;
+pub use rule::{DEFAULT_COMMAND_PREFIXES, Rule};
+pub(crate) use session::Sessions;
 
 pub struct MatchUnion {
     pub description: Cow<'static, str>,
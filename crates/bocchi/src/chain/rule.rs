@@ -1,16 +1,37 @@
-use std::{borrow::Cow, ops};
+use std::{borrow::Cow, ops, time::Duration};
+
+use regex::Regex;
 
 use crate::{
-    chain::Matcher,
-    schema::{Event, Sender},
+    chain::{
+        Matcher,
+        capture::{Args, Captured, Captures, split_args},
+        limit::{LimitScope, Limiter, Quota},
+    },
+    permission::Permissions,
+    schema::{Event, NoticeType, Role, Sender},
 };
 
-#[allow(clippy::enum_variant_names)]
+/// on_command 默认使用的命令前缀
+pub const DEFAULT_COMMAND_PREFIXES: &[&str] = &["#"];
+
+#[allow(clippy::enum_variant_names, clippy::type_complexity)]
 pub enum InnerRule {
     OnEventStatic(&'static (dyn Fn(&Event) -> bool + Send + Sync)),
     OnText(Box<dyn Fn(&str) -> bool + Send + Sync>),
     OnSender(Box<dyn Fn(&Sender) -> bool + Send + Sync>),
     OnEvent(Box<dyn Fn(&Event) -> bool + Send + Sync>),
+    /// 匹配文本的同时提取内容，提取结果可以通过 Context 获取
+    OnTextCapture(Box<dyn Fn(&str, &mut Captured) -> bool + Send + Sync>),
+    /// 需要读取运行时权限配置的规则
+    OnPermissions(Box<dyn Fn(&Event, &Permissions) -> bool + Send + Sync>),
+    /// 频率限制，reply 为 true 时超出限制也视为匹配，由 bot 回复需要等待的时间
+    OnLimit {
+        limiter: Limiter,
+        reply: bool,
+    },
+    /// 消息是否对 bot 说，单独区分以便只在有插件使用时才查询回复的消息
+    ToMe,
 }
 
 pub struct Rule {
@@ -42,6 +63,38 @@ impl Rule {
         }
     }
 
+    /// 匹配对 bot 说的消息：@ 了 bot、回复了 bot 的消息、以 bot 的昵称开头或私聊消息
+    ///
+    /// 开头的 @ 与昵称会从消息中去除，处理器看到的 plain_text 不包含这部分内容
+    ///
+    /// 识别回复 bot 的消息需要调用 get_msg，只有注册的插件中使用了 to_me 时才会进行
+    pub fn to_me() -> Rule {
+        Self {
+            name: "to_me".into(),
+            inner: InnerRule::ToMe,
+        }
+    }
+
+    /// 匹配加好友请求与加群请求／邀请
+    pub fn on_request() -> Rule {
+        Self {
+            name: "on_request".into(),
+            inner: InnerRule::OnEventStatic(&|event: &Event| -> bool {
+                matches!(event, Event::FriendRequest(_) | Event::GroupRequest(_))
+            }),
+        }
+    }
+
+    /// 匹配指定类型的通知事件
+    pub fn on_notice(notice_type: NoticeType) -> Rule {
+        Self {
+            name: format!("on_notice({notice_type:?})").into(),
+            inner: InnerRule::OnEvent(Box::new(move |event: &Event| -> bool {
+                matches!(event, Event::Notice(notice) if notice.notice_type() == notice_type)
+            })),
+        }
+    }
+
     pub fn on_sender_id(user_id: u64) -> Rule {
         Self {
             name: format!("on_sender_id({})", mask_id(user_id)).into(),
@@ -51,6 +104,60 @@ impl Rule {
         }
     }
 
+    /// 匹配超级用户发送的事件，超级用户可以通过 `Context::permissions` 在运行时修改
+    pub fn is_superuser() -> Rule {
+        Self {
+            name: "is_superuser".into(),
+            inner: InnerRule::OnPermissions(Box::new(|event: &Event, permissions: &Permissions| -> bool {
+                event
+                    .try_user_id()
+                    .is_ok_and(|user_id| permissions.is_superuser(user_id))
+            })),
+        }
+    }
+
+    /// 匹配群内身份不低于 role 的发送者，例如 `Rule::role(Role::Admin)` 同时匹配管理员与群主
+    pub fn role(role: Role) -> Rule {
+        Self {
+            name: format!("role({role:?})").into(),
+            inner: InnerRule::OnSender(Box::new(move |sender: &Sender| -> bool {
+                sender.group_role().is_some_and(|sender_role| sender_role >= role)
+            })),
+        }
+    }
+
+    /// 同一范围内两次触发之间至少间隔 duration，超出限制时默认不匹配
+    ///
+    /// 只有整个 Matcher 匹配成功、事件交给处理器时才会记录一次触发
+    pub fn cooldown(scope: LimitScope, duration: Duration) -> Rule {
+        Self {
+            name: format!("cooldown({scope:?}, {duration:?})").into(),
+            inner: InnerRule::OnLimit {
+                limiter: Limiter::new(scope, Quota::cooldown(duration)),
+                reply: false,
+            },
+        }
+    }
+
+    /// 同一范围内的触发次数不超过 quota，使用方式与 cooldown 相同
+    pub fn rate_limit(scope: LimitScope, quota: Quota) -> Rule {
+        Self {
+            name: format!("rate_limit({scope:?}, {}/{:?})", quota.max, quota.period).into(),
+            inner: InnerRule::OnLimit {
+                limiter: Limiter::new(scope, quota),
+                reply: false,
+            },
+        }
+    }
+
+    /// 超出频率限制时回复需要等待的时间，而不是静默忽略，仅对 cooldown 与 rate_limit 生效
+    pub fn reply_on_limit(mut self) -> Rule {
+        if let InnerRule::OnLimit { reply, .. } = &mut self.inner {
+            *reply = true;
+        }
+        self
+    }
+
     pub fn on_group_id(group_id: u64) -> Rule {
         Self {
             name: format!("on_group_id({})", mask_id(group_id)).into(),
@@ -82,6 +189,103 @@ impl Rule {
             text.ends_with(suffix.trim())
         })
     }
+
+    fn on_text_capture(
+        name: Cow<'static, str>,
+        capture: impl Fn(&str, &mut Captured) -> bool + Send + Sync + 'static,
+    ) -> Rule {
+        Self {
+            name,
+            inner: InnerRule::OnTextCapture(Box::new(move |text, captured| capture(text.trim(), captured))),
+        }
+    }
+
+    /// 使用正则表达式匹配文本，分组内容可以通过 `ctx.captures()` 获取
+    pub fn on_regex(regex: Regex) -> Rule {
+        Self::on_text_capture(format!("on_regex({regex})").into(), move |text, captured| {
+            let Some(captures) = regex.captures(text) else {
+                return false;
+            };
+            captured.captures = Some(Captures::from_regex(&regex, &captures));
+            true
+        })
+    }
+
+    /// 文本中包含任意一个关键词时匹配，匹配到的关键词可以通过 `ctx.captures().get(0)` 获取
+    pub fn on_keyword(keywords: &[&'static str]) -> Rule {
+        let keywords = keywords.to_vec();
+        Self::on_text_capture(
+            format!("on_keyword({})", keywords.join("|")).into(),
+            move |text, captured| {
+                let Some(keyword) = keywords.iter().find(|keyword| text.contains(*keyword)) else {
+                    return false;
+                };
+                captured.captures = Some(Captures::from_keyword(keyword));
+                true
+            },
+        )
+    }
+
+    /// 匹配以 `#` 开头的命令，如 `#gpt 你好`，命令参数可以通过 `ctx.args()` 获取
+    pub fn on_command(name: &'static str, aliases: &[&'static str]) -> Rule {
+        Self::on_command_with_prefixes(name, aliases, DEFAULT_COMMAND_PREFIXES)
+    }
+
+    /// 使用自定义的命令前缀匹配命令，前缀可以为空字符串
+    pub fn on_command_with_prefixes(name: &'static str, aliases: &[&'static str], prefixes: &[&'static str]) -> Rule {
+        let names = std::iter::once(name).chain(aliases.iter().copied()).collect::<Vec<_>>();
+        let prefixes = prefixes.to_vec();
+        let prefix = prefixes.first().copied().unwrap_or_default();
+        let display = names
+            .iter()
+            .map(|name| format!("{prefix}{name}"))
+            .collect::<Vec<_>>()
+            .join("|");
+        let display = format!("on_command({display})");
+        Self::on_text_capture(display.into(), move |text, captured| {
+            let rest = prefixes
+                .iter()
+                .filter_map(|prefix| text.strip_prefix(prefix))
+                .flat_map(|text| names.iter().filter_map(move |name| text.strip_prefix(name)))
+                // 命令名之后需要是空白或文本结尾，避免 #gpt 匹配到 #gpt_history
+                .find(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace));
+            let Some(rest) = rest else {
+                return false;
+            };
+            let raw = rest.trim().to_owned();
+            captured.args = Some(Args {
+                list: split_args(&raw),
+                raw,
+            });
+            true
+        })
+    }
+}
+
+impl Rule {
+    pub(crate) fn is_match(&self, event: &Event, permissions: &Permissions, captured: &mut Captured) -> bool {
+        match &self.inner {
+            // 非消息事件没有文本与发送者，直接视为不匹配
+            InnerRule::OnText(handler) => event.try_plain_text().is_ok_and(|text| handler(&text)),
+            InnerRule::OnSender(handler) => event.try_sender().is_ok_and(handler),
+            InnerRule::OnEventStatic(handler) => handler(event),
+            InnerRule::OnEvent(handler) => handler(event),
+            InnerRule::OnTextCapture(handler) => event.try_plain_text().is_ok_and(|text| handler(&text, captured)),
+            InnerRule::OnPermissions(handler) => handler(event, permissions),
+            InnerRule::OnLimit { limiter, reply } => match limiter.check(event) {
+                Ok(hit) => {
+                    captured.hits.extend(hit);
+                    true
+                }
+                Err(wait) if *reply => {
+                    captured.limited = Some(wait);
+                    true
+                }
+                Err(_) => false,
+            },
+            InnerRule::ToMe => event.is_to_me(),
+        }
+    }
 }
 
 fn mask_id(id: u64) -> String {
@@ -93,12 +297,26 @@ fn mask_id(id: u64) -> String {
     format!("{}{}{}", &id[..3], "*".repeat(id.len() - 6), &id[id.len() - 3..])
 }
 
-impl ops::BitAnd<Rule> for Rule {
+impl<T: Into<Matcher>> ops::BitAnd<T> for Rule {
     type Output = Matcher;
 
-    fn bitand(self, rhs: Rule) -> Matcher {
-        Matcher {
-            condition: vec![self, rhs],
-        }
+    fn bitand(self, rhs: T) -> Matcher {
+        Matcher::from(self) & rhs
+    }
+}
+
+impl<T: Into<Matcher>> ops::BitOr<T> for Rule {
+    type Output = Matcher;
+
+    fn bitor(self, rhs: T) -> Matcher {
+        Matcher::from(self) | rhs
+    }
+}
+
+impl ops::Not for Rule {
+    type Output = Matcher;
+
+    fn not(self) -> Matcher {
+        !Matcher::from(self)
     }
 }
@@ -0,0 +1,163 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, Ordering},
+};
+
+use tokio::sync::oneshot;
+
+use crate::chain::{Context, Matcher};
+
+/// 等待同一用户在同一会话中发送下一条消息的处理器
+struct Waiter {
+    /// 按照开始等待的顺序递增
+    id: u64,
+    /// 私聊时为 None
+    group_id: Option<u64>,
+    user_id: u64,
+    matcher: Arc<Matcher>,
+    sender: oneshot::Sender<Context>,
+}
+
+/// 所有正在等待后续消息的会话，收到的消息会优先交给会话，而不是分发给插件
+#[derive(Default)]
+pub(crate) struct Sessions {
+    waiters: Mutex<Vec<Waiter>>,
+    next_id: AtomicU64,
+}
+
+impl Sessions {
+    pub(crate) fn wait(&self, group_id: Option<u64>, user_id: u64, matcher: Matcher) -> oneshot::Receiver<Context> {
+        let (sender, receiver) = oneshot::channel();
+        let mut waiters = self.waiters.lock().unwrap();
+        // 顺便清理已经超时的会话
+        waiters.retain(|waiter| !waiter.sender.is_closed());
+        waiters.push(Waiter {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            group_id,
+            user_id,
+            matcher: Arc::new(matcher),
+            sender,
+        });
+        receiver
+    }
+
+    /// 将消息交给最早开始等待且匹配的会话，没有会话接收时原样返回
+    ///
+    /// 需要按照收到事件的顺序调用，保证同一用户连续发送的消息按顺序交给会话
+    pub(crate) fn feed(&self, context: Context) -> Option<Context> {
+        if context.event.try_message().is_err() {
+            return Some(context);
+        }
+        let (Some(user_id), group_id) = (context.event.try_user_id().ok(), context.event.try_group_id().ok()) else {
+            return Some(context);
+        };
+        // 匹配条件在锁外求值，只在锁内取出候选的会话
+        let candidates = {
+            let mut waiters = self.waiters.lock().unwrap();
+            waiters.retain(|waiter| !waiter.sender.is_closed());
+            waiters
+                .iter()
+                .filter(|waiter| waiter.user_id == user_id && waiter.group_id == group_id)
+                .map(|waiter| (waiter.id, waiter.matcher.clone()))
+                .collect::<Vec<_>>()
+        };
+        let mut context = context;
+        for (id, matcher) in candidates {
+            let Some(captured) = matcher.capture(&context.event, &context.config.permissions) else {
+                continue;
+            };
+            // 会话可能在求值期间超时
+            let Some(waiter) = self.take(id) else {
+                continue;
+            };
+            if captured.commit_hits().is_err() {
+                self.restore(waiter);
+                continue;
+            }
+            match waiter.sender.send(Context {
+                captured: Arc::new(captured),
+                ..context
+            }) {
+                Ok(()) => return None,
+                // 会话恰好在此时超时，交给下一个会话
+                Err(returned) => context = returned,
+            }
+        }
+        Some(context)
+    }
+
+    fn take(&self, id: u64) -> Option<Waiter> {
+        let mut waiters = self.waiters.lock().unwrap();
+        let index = waiters.iter().position(|waiter| waiter.id == id)?;
+        Some(waiters.remove(index))
+    }
+
+    /// 放回取出的会话，保持按照开始等待的顺序排列
+    fn restore(&self, waiter: Waiter) {
+        let mut waiters = self.waiters.lock().unwrap();
+        let index = waiters.partition_point(|other| other.id < waiter.id);
+        waiters.insert(index, waiter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        bot::BotConfig,
+        chain::{
+            Rule,
+            handler::tests::{context, message},
+        },
+        schema::Event,
+    };
+
+    async fn feed(sessions: &Sessions, event: Event) -> Option<Context> {
+        sessions.feed(context(event, BotConfig::default()).await)
+    }
+
+    #[tokio::test]
+    async fn test_feed() {
+        let sessions = Sessions::default();
+        let mut receiver = sessions.wait(Some(1), 2, Matcher::default());
+        // 其他用户、其他群组与私聊中的消息不会交给会话
+        assert!(feed(&sessions, message(Some(1), 3, "a")).await.is_some());
+        assert!(feed(&sessions, message(Some(4), 2, "a")).await.is_some());
+        assert!(feed(&sessions, message(None, 2, "a")).await.is_some());
+        assert!(receiver.try_recv().is_err());
+        assert!(feed(&sessions, message(Some(1), 2, "b")).await.is_none());
+        assert_eq!(receiver.await.unwrap().event.plain_text(), "b");
+        // 会话只接收一条消息
+        assert!(feed(&sessions, message(Some(1), 2, "c")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_feed_order() {
+        let sessions = Sessions::default();
+        let yes = sessions.wait(None, 2, Rule::on_exact_match("yes").into());
+        let any = sessions.wait(None, 2, Matcher::default());
+        let later = sessions.wait(None, 2, Matcher::default());
+        // 不满足条件的会话被跳过，满足条件的会话中最早开始等待的优先
+        assert!(feed(&sessions, message(None, 2, "no")).await.is_none());
+        assert_eq!(any.await.unwrap().event.plain_text(), "no");
+        assert!(feed(&sessions, message(None, 2, "yes")).await.is_none());
+        assert_eq!(yes.await.unwrap().event.plain_text(), "yes");
+        assert!(feed(&sessions, message(None, 2, "yes")).await.is_none());
+        assert_eq!(later.await.unwrap().event.plain_text(), "yes");
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let sessions = Sessions::default();
+        let receiver = sessions.wait(None, 2, Matcher::default());
+        // receiver 被丢弃后会话失效，消息按照正常流程分发
+        drop(receiver);
+        assert!(feed(&sessions, message(None, 2, "a")).await.is_some());
+        assert!(sessions.waiters.lock().unwrap().is_empty());
+        let ctx = context(message(None, 2, "a"), BotConfig::default()).await;
+        let wait = ctx.wait_for(Matcher::default(), Duration::from_millis(10)).await;
+        assert!(wait.unwrap().is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use thiserror::Error;
 
-use crate::schema::ResponseBody;
-
 #[derive(Error, Debug)]
 pub enum ApiError {
-    #[error("Invalid Response Type: {0:?}")]
-    ResponseTypeError(ResponseBody),
+    #[error("Invalid Response Type: {0}")]
+    ResponseTypeError(#[from] serde_json::Error),
+    /// OneBot 实现返回了失败状态，例如 bot 被禁言、消息不存在等
+    #[error("Api call failed with retcode {retcode}: {message}")]
+    Failed { retcode: i64, message: String },
 }
@@ -8,5 +8,10 @@ pub mod bot;
 pub mod caller;
 pub mod chain;
 pub mod error;
+pub mod media;
+pub mod middleware;
+pub mod permission;
 pub mod plugin;
+pub mod schedule;
 pub mod schema;
+pub mod switch;
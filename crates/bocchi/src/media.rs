@@ -0,0 +1,308 @@
+//! 图片、语音等媒体文件的传输方式
+//!
+//! 插件只需要提供字节内容或本地路径，发送时按照 [`MediaTransport`] 转换为 OneBot 实现可以读取的 file 参数，
+//! 期间产生的临时文件与 HTTP 链接会在消息发送完成后自动清理
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use dashmap::DashMap;
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::Full;
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use tokio::{fs, net::TcpListener, runtime::Handle, time};
+
+use crate::schema::{MessageContent, MessageSegment};
+
+/// 待发送的媒体内容
+#[derive(Debug, Clone)]
+pub enum Media {
+    Bytes(Bytes),
+    Path(PathBuf),
+}
+
+impl From<Vec<u8>> for Media {
+    fn from(bytes: Vec<u8>) -> Self {
+        Media::Bytes(bytes.into())
+    }
+}
+
+impl From<Bytes> for Media {
+    fn from(bytes: Bytes) -> Self {
+        Media::Bytes(bytes)
+    }
+}
+
+impl From<PathBuf> for Media {
+    fn from(path: PathBuf) -> Self {
+        Media::Path(path)
+    }
+}
+
+impl From<&Path> for Media {
+    fn from(path: &Path) -> Self {
+        Media::Path(path.to_owned())
+    }
+}
+
+impl Media {
+    /// 不经过 MediaStore 直接转换，本地路径使用 file://，字节内容使用 base64://
+    pub(crate) fn inline(&self) -> String {
+        match self {
+            Media::Bytes(bytes) => base64_uri(bytes),
+            Media::Path(path) => file_uri(path),
+        }
+    }
+}
+
+/// 媒体文件的传输方式，需要根据 bot 与 OneBot 实现的部署方式选择
+#[derive(Debug, Clone, Default)]
+pub enum MediaTransport {
+    /// 以 base64 内联在请求中，不依赖文件系统与网络，但会显著增大请求体积
+    #[default]
+    Base64,
+    /// 通过 file:// 传递，要求 bot 与 OneBot 实现能够访问同一文件系统，字节内容会先写入临时文件
+    File,
+    /// bot 在 listen_address 上提供 HTTP 服务，OneBot 实现通过 `{base_url}/{token}` 下载，链接在消息发送完成后失效
+    Http { listen_address: String, base_url: String },
+}
+
+/// 接受连接失败后重试前的等待时间，避免文件描述符耗尽等持续性错误导致空转
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+type Served = Arc<DashMap<String, Media>>;
+
+/// 按照传输方式将媒体内容转换为 file 参数
+pub(crate) struct MediaStore {
+    transport: MediaTransport,
+    served: Served,
+}
+
+impl MediaStore {
+    /// 使用 HTTP 传输时会在这里启动 HTTP 服务
+    pub(crate) async fn new(transport: MediaTransport) -> Result<Self> {
+        let served: Served = Arc::new(DashMap::new());
+        if let MediaTransport::Http { listen_address, .. } = &transport {
+            let listener = TcpListener::bind(listen_address).await?;
+            info!("Serving media on {}", listener.local_addr()?);
+            tokio::spawn(serve(listener, served.clone()));
+        }
+        Ok(Self { transport, served })
+    }
+
+    /// 转换消息中所有待上传的媒体，返回的 MediaGuard 需要保留到消息发送完成
+    pub(crate) async fn resolve(
+        &self,
+        segments: Vec<MessageSegment>,
+        media: HashMap<String, Media>,
+    ) -> Result<(MessageContent, Vec<MediaGuard>)> {
+        let mut files = HashMap::with_capacity(media.len());
+        let mut guards = Vec::with_capacity(media.len());
+        for (placeholder, media) in media {
+            let (file, guard) = self.upload(media).await?;
+            files.insert(placeholder, file);
+            guards.extend(guard);
+        }
+        let mut segments = segments;
+        replace_files(&mut segments, &mut |file| {
+            if let Some(resolved) = files.get(file.as_str()) {
+                resolved.clone_into(file);
+            }
+        });
+        Ok((MessageContent::Segment(segments), guards))
+    }
+
+    async fn upload(&self, media: Media) -> Result<(String, Option<MediaGuard>)> {
+        Ok(match (&self.transport, media) {
+            (MediaTransport::Base64, Media::Bytes(bytes)) => (base64_uri(&bytes), None),
+            (MediaTransport::Base64, Media::Path(path)) => (base64_uri(&fs::read(path).await?), None),
+            (MediaTransport::File, Media::Path(path)) => (file_uri(&path), None),
+            (MediaTransport::File, Media::Bytes(bytes)) => {
+                let path = std::env::temp_dir().join(format!("bocchi-{}", token()));
+                fs::write(&path, bytes).await?;
+                (file_uri(&path), Some(MediaGuard::TempFile(path)))
+            }
+            (MediaTransport::Http { base_url, .. }, media) => {
+                let token = token();
+                self.served.insert(token.clone(), media);
+                (
+                    format!("{}/{}", base_url.trim_end_matches('/'), token),
+                    Some(MediaGuard::Served(token, self.served.clone())),
+                )
+            }
+        })
+    }
+}
+
+/// 在消息发送完成后清理临时文件与 HTTP 链接
+pub(crate) enum MediaGuard {
+    TempFile(PathBuf),
+    Served(String, Served),
+}
+
+impl Drop for MediaGuard {
+    fn drop(&mut self) {
+        match self {
+            MediaGuard::TempFile(path) => {
+                let path = std::mem::take(path);
+                let remove = move || {
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        warn!("Failed to remove temp file {}: {e:?}", path.display());
+                    }
+                };
+                // 删除文件会阻塞，在运行时中交给阻塞线程执行
+                match Handle::try_current() {
+                    Ok(handle) => drop(handle.spawn_blocking(remove)),
+                    Err(_) => remove(),
+                }
+            }
+            MediaGuard::Served(token, served) => {
+                served.remove(token);
+            }
+        }
+    }
+}
+
+/// 遍历消息段中所有的 file 参数，包括合并转发节点中的内容
+pub(crate) fn replace_files(segments: &mut [MessageSegment], f: &mut impl FnMut(&mut String)) {
+    for segment in segments {
+        match segment {
+            MessageSegment::Image { file, .. }
+            | MessageSegment::Record { file, .. }
+            | MessageSegment::Video { file, .. } => f(file),
+            MessageSegment::Node {
+                content: Some(MessageContent::Segment(segments)),
+                ..
+            } => replace_files(segments, f),
+            _ => (),
+        }
+    }
+}
+
+/// 生成占位符与 HTTP 链接使用的随机字符串
+pub(crate) fn token() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+fn base64_uri(bytes: &[u8]) -> String {
+    format!("base64://{}", base64_simd::STANDARD.encode_to_string(bytes))
+}
+
+fn file_uri(path: &Path) -> String {
+    format!("file://{}", path.to_string_lossy())
+}
+
+async fn serve(listener: TcpListener, served: Served) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                error!("Failed to accept media request: {e:?}");
+                time::sleep(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
+        let served = served.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle_request(request, served.clone()));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                error!("Failed to serve media: {e:?}");
+            }
+        });
+    }
+}
+
+async fn handle_request(request: Request<Incoming>, served: Served) -> Result<Response<Full<Bytes>>, Infallible> {
+    let media = (request.method() == Method::GET)
+        .then(|| served.get(request.uri().path().trim_start_matches('/')))
+        .flatten()
+        .map(|media| media.value().clone());
+    let body = match media {
+        Some(Media::Bytes(bytes)) => Some(bytes),
+        Some(Media::Path(path)) => fs::read(path).await.ok().map(Bytes::from),
+        None => None,
+    };
+    let mut response = Response::new(Full::new(body.clone().unwrap_or_default()));
+    if body.is_none() {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::MessageBuilder;
+
+    fn files(content: &MessageContent) -> Vec<String> {
+        let MessageContent::Segment(segments) = content else {
+            panic!("resolved message should be segments");
+        };
+        let mut files = Vec::new();
+        replace_files(&mut segments.clone(), &mut |file| files.push(file.clone()));
+        files
+    }
+
+    #[tokio::test]
+    async fn test_resolve_base64() {
+        let store = MediaStore::new(MediaTransport::Base64).await.unwrap();
+        let (segments, media) = MessageBuilder::new()
+            .image_bytes(b"bocchi".to_vec())
+            .image_url("https://example.com/a.png")
+            .node(10000, "bocchi", MessageBuilder::new().record_media(b"kita".to_vec()))
+            .into_parts();
+        let (content, guards) = store.resolve(segments, media).await.unwrap();
+        assert!(guards.is_empty());
+        assert_eq!(
+            files(&content),
+            ["base64://Ym9jY2hp", "https://example.com/a.png", "base64://a2l0YQ=="]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_temp_file() {
+        let store = MediaStore::new(MediaTransport::File).await.unwrap();
+        let (segments, media) = MessageBuilder::new().image_bytes(b"bocchi".to_vec()).into_parts();
+        let (content, guards) = store.resolve(segments, media).await.unwrap();
+        let [file] = files(&content).try_into().unwrap();
+        let path = PathBuf::from(file.strip_prefix("file://").unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), b"bocchi");
+        // 临时文件在 guard 释放后删除
+        drop(guards);
+        for _ in 0..100 {
+            if !path.exists() {
+                return;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("temp file {} is not removed", path.display());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_http() {
+        let store = MediaStore::new(MediaTransport::Http {
+            listen_address: "127.0.0.1:0".to_string(),
+            base_url: "http://127.0.0.1:8081/".to_string(),
+        })
+        .await
+        .unwrap();
+        let (segments, media) = MessageBuilder::new().image_bytes(b"bocchi".to_vec()).into_parts();
+        let (content, guards) = store.resolve(segments, media).await.unwrap();
+        let [file] = files(&content).try_into().unwrap();
+        let token = file.strip_prefix("http://127.0.0.1:8081/").unwrap();
+        assert!(store.served.contains_key(token));
+        // 链接在 guard 释放后失效
+        drop(guards);
+        assert!(!store.served.contains_key(token));
+    }
+}
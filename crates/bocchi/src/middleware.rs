@@ -0,0 +1,94 @@
+use std::{fmt, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    chain::{Context, MatchUnion, Matcher},
+    schema::{MessageContent, SendMsgResult},
+};
+
+/// 中间件，用于实现黑名单、日志、统计等与具体插件无关的功能，通过 `Bot::register_middleware` 注册
+///
+/// 多个中间件按照注册顺序调用，around_handler 中先注册的中间件位于外层
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// 中间件的名称，用于日志
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// 在匹配之前调用，返回 false 时丢弃该事件，被丢弃的消息也不会被 `Context::wait_for` 等待到
+    ///
+    /// 可以通过 `Arc::make_mut(&mut context.event)` 修改事件，或向 `context.extensions` 中写入数据供处理器读取
+    ///
+    /// 此时回复 bot 的消息还没有被识别，`event.is_to_me()` 只反映 @ 与昵称
+    async fn before_match(&self, context: &mut Context) -> Result<bool> {
+        let _ = context;
+        Ok(true)
+    }
+
+    /// 包裹每一次处理器的调用，需要调用 `next.run(context)` 继续执行，不调用时视为跳过该处理器
+    async fn around_handler(&self, context: Context, next: Next<'_>) -> Result<bool> {
+        next.run(context).await
+    }
+
+    /// 通过 Context 发送消息之后调用
+    async fn after_send(&self, context: &Context, message: &MessageContent, result: &Result<SendMsgResult>) {
+        let _ = (context, message, result);
+    }
+}
+
+impl fmt::Debug for dyn Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 剩余的中间件与最终调用的处理器
+pub struct Next<'a> {
+    plugin: &'a str,
+    match_union: &'a MatchUnion,
+    middlewares: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(plugin: &'a str, match_union: &'a MatchUnion, middlewares: &'a [Arc<dyn Middleware>]) -> Self {
+        Self {
+            plugin,
+            match_union,
+            middlewares,
+        }
+    }
+
+    /// 处理器所属插件的名称
+    pub fn plugin(&self) -> &'a str {
+        self.plugin
+    }
+
+    pub fn description(&self) -> &'a str {
+        &self.match_union.description
+    }
+
+    pub fn matcher(&self) -> &'a Matcher {
+        &self.match_union.matcher
+    }
+
+    /// 调用下一个中间件，没有剩余的中间件时调用处理器
+    pub async fn run(self, context: Context) -> Result<bool> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => {
+                first
+                    .around_handler(
+                        context,
+                        Next {
+                            middlewares: rest,
+                            ..self
+                        },
+                    )
+                    .await
+            }
+            None => (self.match_union.handler)(context).await,
+        }
+    }
+}
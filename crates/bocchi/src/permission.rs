@@ -0,0 +1,135 @@
+use std::{collections::HashSet, sync::RwLock};
+
+use dashmap::DashMap;
+
+use crate::schema::Event;
+
+/// 插件的访问控制列表，拒绝列表优先于允许列表，允许列表为空时不做限制
+///
+/// 允许列表中的群组只对群消息生效，配置了 allow_groups 的插件不会处理私聊等不属于群组的事件
+#[derive(Debug, Clone, Default)]
+pub struct Acl {
+    pub allow_groups: HashSet<u64>,
+    pub deny_groups: HashSet<u64>,
+    pub allow_users: HashSet<u64>,
+    pub deny_users: HashSet<u64>,
+}
+
+impl Acl {
+    pub fn is_allowed(&self, group_id: Option<u64>, user_id: Option<u64>) -> bool {
+        let check = |id: Option<u64>, allow: &HashSet<u64>, deny: &HashSet<u64>| match id {
+            Some(id) => !deny.contains(&id) && (allow.is_empty() || allow.contains(&id)),
+            None => allow.is_empty(),
+        };
+        check(group_id, &self.allow_groups, &self.deny_groups) && check(user_id, &self.allow_users, &self.deny_users)
+    }
+}
+
+/// 超级用户与各个插件的访问控制列表，运行期间可以随时修改
+#[derive(Debug, Default)]
+pub struct Permissions {
+    superusers: RwLock<HashSet<u64>>,
+    /// 以插件名为键
+    acls: DashMap<String, Acl>,
+}
+
+impl Permissions {
+    pub fn is_superuser(&self, user_id: u64) -> bool {
+        self.superusers.read().unwrap().contains(&user_id)
+    }
+
+    pub fn add_superuser(&self, user_id: u64) {
+        self.superusers.write().unwrap().insert(user_id);
+    }
+
+    pub fn remove_superuser(&self, user_id: u64) {
+        self.superusers.write().unwrap().remove(&user_id);
+    }
+
+    pub fn superusers(&self) -> Vec<u64> {
+        self.superusers.read().unwrap().iter().copied().collect()
+    }
+
+    /// 获取插件当前的访问控制列表，未配置时返回不做限制的列表
+    pub fn acl(&self, plugin: &str) -> Acl {
+        self.acls.get(plugin).map(|acl| acl.clone()).unwrap_or_default()
+    }
+
+    pub fn set_acl(&self, plugin: impl Into<String>, acl: Acl) {
+        self.acls.insert(plugin.into(), acl);
+    }
+
+    /// 原地修改插件的访问控制列表，例如 `update_acl("IP 插件", |acl| { acl.deny_groups.insert(123); })`
+    pub fn update_acl(&self, plugin: impl Into<String>, f: impl FnOnce(&mut Acl)) {
+        f(&mut self.acls.entry(plugin.into()).or_default());
+    }
+
+    /// 插件未在运行时配置过访问控制列表时，使用插件自带的列表
+    pub(crate) fn init_acl(&self, plugin: &str, acl: &Acl) {
+        self.acls.entry(plugin.to_owned()).or_insert_with(|| acl.clone());
+    }
+
+    /// 判断插件是否可以处理该事件，超级用户不受访问控制列表限制
+    pub(crate) fn is_allowed(&self, plugin: &str, event: &Event) -> bool {
+        let user_id = event.try_user_id().ok();
+        if user_id.is_some_and(|user_id| self.is_superuser(user_id)) {
+            return true;
+        }
+        self.acls
+            .get(plugin)
+            .is_none_or(|acl| acl.is_allowed(event.try_group_id().ok(), user_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acl_is_allowed() {
+        // 列表全部为空时不做限制
+        let acl = Acl::default();
+        assert!(acl.is_allowed(Some(1), Some(2)));
+        assert!(acl.is_allowed(None, Some(2)));
+        let acl = Acl {
+            allow_groups: HashSet::from([1, 3]),
+            deny_groups: HashSet::from([3]),
+            deny_users: HashSet::from([4]),
+            ..Default::default()
+        };
+        assert!(acl.is_allowed(Some(1), Some(2)));
+        // 拒绝列表优先于允许列表
+        assert!(!acl.is_allowed(Some(3), Some(2)));
+        assert!(!acl.is_allowed(Some(1), Some(4)));
+        assert!(!acl.is_allowed(Some(5), Some(2)));
+        // 配置了 allow_groups 时不处理不属于群组的事件
+        assert!(!acl.is_allowed(None, Some(2)));
+        let acl = Acl {
+            allow_users: HashSet::from([2]),
+            ..Default::default()
+        };
+        assert!(acl.is_allowed(None, Some(2)));
+        assert!(!acl.is_allowed(Some(1), Some(5)));
+        assert!(!acl.is_allowed(Some(1), None));
+    }
+
+    #[test]
+    fn test_superuser_bypasses_acl() {
+        let event: Event = serde_json::from_str(
+            r#"{"time":1700000000,"self_id":10000,"post_type":"notice","notice_type":"group_recall","group_id":1,"user_id":2,"operator_id":2,"message_id":3}"#,
+        )
+        .unwrap();
+        let permissions = Permissions::default();
+        assert!(permissions.is_allowed("echo", &event));
+        permissions.update_acl("echo", |acl| {
+            acl.deny_groups.insert(1);
+        });
+        assert!(!permissions.is_allowed("echo", &event));
+        permissions.add_superuser(2);
+        assert!(permissions.is_allowed("echo", &event));
+        // 运行时配置的列表不会被插件自带的列表覆盖
+        permissions.remove_superuser(2);
+        permissions.init_acl("echo", &Acl::default());
+        assert!(!permissions.is_allowed("echo", &event));
+    }
+}
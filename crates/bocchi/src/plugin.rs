@@ -2,12 +2,26 @@ use std::{borrow::Cow, future::Future, pin::Pin, sync::Arc};
 
 use anyhow::Result;
 
-use crate::chain::{Context, MatchUnion, Matcher};
+use crate::{
+    adapter::Caller,
+    chain::{Context, IntoHandler, MatchUnion, Matcher},
+    permission::Acl,
+    schedule::{Job, Trigger},
+};
+
+/// 生命周期钩子，参数为可用于调用 API 的 Caller
+pub type Hook = Box<dyn Fn(Arc<dyn Caller>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
 
 pub struct Plugin {
     pub name: Cow<'static, str>,
     pub description: Cow<'static, str>,
     match_unions: Vec<Arc<MatchUnion>>,
+    jobs: Vec<Arc<Job>>,
+    startup_hooks: Vec<Hook>,
+    shutdown_hooks: Vec<Hook>,
+    bot_connect_hooks: Vec<Hook>,
+    bot_disconnect_hooks: Vec<Hook>,
+    acl: Acl,
 }
 
 impl Plugin {
@@ -16,6 +30,12 @@ impl Plugin {
             name: name.into(),
             description: description.into(),
             match_unions: Vec::new(),
+            jobs: Vec::new(),
+            startup_hooks: Vec::new(),
+            shutdown_hooks: Vec::new(),
+            bot_connect_hooks: Vec::new(),
+            bot_disconnect_hooks: Vec::new(),
+            acl: Acl::default(),
         }
     }
 
@@ -25,16 +45,126 @@ impl Plugin {
         M: Into<Matcher>,
         H: Fn(Context) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = Result<bool>> + Send + 'static,
+    {
+        self.on_extract(description, priority, matcher, handler);
+    }
+
+    /// 注册参数为任意 FromContext 类型的处理器，例如 `async fn handler(user_id: UserId, args: CommandArgs) -> Result<Outcome>`
+    ///
+    /// 任意一个参数提取失败时视为处理器不适用于该事件，事件继续交给优先级更低的处理器
+    pub fn on_extract<D, M, H, Args>(&mut self, description: D, priority: i32, matcher: M, handler: H)
+    where
+        D: Into<Cow<'static, str>>,
+        M: Into<Matcher>,
+        H: IntoHandler<Args>,
     {
         self.match_unions.push(Arc::new(MatchUnion::new(
             description.into(),
             priority,
             matcher.into(),
-            Box::new(move |ctx| Box::pin(handler(ctx)) as Pin<Box<dyn Future<Output = Result<bool>> + Send + 'static>>),
+            handler.into_handler(),
         )));
     }
 
+    /// 注册定时任务，trigger 可以是 cron 表达式（如 `"0 0 9 * * *"`）、Duration 或 Trigger，无效时返回错误
+    ///
+    /// 任务在 bot 启动后开始计时，可以通过 Caller 向任意群组或用户发送消息
+    pub fn schedule<D, T, H, Fut>(&mut self, description: D, trigger: T, job: H) -> Result<()>
+    where
+        D: Into<Cow<'static, str>>,
+        T: TryInto<Trigger>,
+        T::Error: Into<anyhow::Error>,
+        H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Arc::new(Job {
+            description: description.into(),
+            trigger: trigger.try_into().map_err(Into::into)?,
+            hook: into_hook(job),
+        }));
+        Ok(())
+    }
+
+    /// bot 启动时调用，所有插件的启动钩子完成后才会开始处理事件，适合预热缓存或启动后台任务
+    ///
+    /// 此时可能尚未与 OneBot 实现建立连接，需要调用 API 的初始化请使用 on_bot_connect
+    pub fn on_startup<H, Fut>(&mut self, hook: H)
+    where
+        H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.startup_hooks.push(into_hook(hook));
+    }
+
+    /// bot 停止时调用，适合保存状态或清理资源
+    pub fn on_shutdown<H, Fut>(&mut self, hook: H)
+    where
+        H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.shutdown_hooks.push(into_hook(hook));
+    }
+
+    /// 与 OneBot 实现建立连接后调用，断线重连成功后也会再次调用
+    ///
+    /// HTTP 适配器没有长连接，在开始监听上报后调用一次
+    pub fn on_bot_connect<H, Fut>(&mut self, hook: H)
+    where
+        H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.bot_connect_hooks.push(into_hook(hook));
+    }
+
+    /// 与 OneBot 实现的连接断开后调用，此时通过 Caller 调用 API 会失败
+    ///
+    /// HTTP 适配器在停止监听上报时调用，此时仍然可以调用 API
+    pub fn on_bot_disconnect<H, Fut>(&mut self, hook: H)
+    where
+        H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.bot_disconnect_hooks.push(into_hook(hook));
+    }
+
+    /// 设置插件默认的访问控制列表，运行期间通过 Permissions 修改的列表优先
+    pub fn set_acl(&mut self, acl: Acl) {
+        self.acl = acl;
+    }
+
+    pub(crate) fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
     pub(crate) fn match_unions(&self) -> &[Arc<MatchUnion>] {
         &self.match_unions
     }
+
+    pub(crate) fn jobs(&self) -> &[Arc<Job>] {
+        &self.jobs
+    }
+
+    pub(crate) fn startup_hooks(&self) -> &[Hook] {
+        &self.startup_hooks
+    }
+
+    pub(crate) fn shutdown_hooks(&self) -> &[Hook] {
+        &self.shutdown_hooks
+    }
+
+    pub(crate) fn bot_connect_hooks(&self) -> &[Hook] {
+        &self.bot_connect_hooks
+    }
+
+    pub(crate) fn bot_disconnect_hooks(&self) -> &[Hook] {
+        &self.bot_disconnect_hooks
+    }
+}
+
+fn into_hook<H, Fut>(hook: H) -> Hook
+where
+    H: Fn(Arc<dyn Caller>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    Box::new(move |caller| Box::pin(hook(caller)) as Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>)
 }
@@ -0,0 +1,232 @@
+use std::{borrow::Cow, fmt, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{Result, anyhow, ensure};
+use chrono::{DateTime, Local, TimeDelta};
+use tokio::{sync::watch, time};
+
+use crate::{adapter::Caller, plugin::Hook};
+
+/// 实际触发时间晚于计划时间超过该时长时视为错过
+const MISSED_GRACE: TimeDelta = TimeDelta::seconds(1);
+
+/// 任务仍在执行或进程被挂起而错过触发时间时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissedRun {
+    /// 忽略错过的触发，等待下一次触发
+    #[default]
+    Skip,
+    /// 立即补执行一次，错过多次时也只补一次
+    RunOnce,
+}
+
+#[derive(Debug, Clone)]
+enum TriggerKind {
+    Cron(Box<cron::Schedule>),
+    Interval(Duration),
+}
+
+/// 定时任务的触发时间，可以由 cron 表达式或 Duration 转换得到
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    kind: TriggerKind,
+    missed: MissedRun,
+}
+
+impl Trigger {
+    /// 包含秒字段的 cron 表达式，按照本地时区计算，例如 `0 0 9 * * *` 表示每天 9 点，也支持 `@daily` 等简写
+    pub fn cron(expression: &str) -> Result<Self> {
+        let schedule =
+            cron::Schedule::from_str(expression).map_err(|e| anyhow!("Invalid cron expression {expression:?}: {e}"))?;
+        Ok(Self {
+            kind: TriggerKind::Cron(Box::new(schedule)),
+            missed: MissedRun::default(),
+        })
+    }
+
+    /// 从 bot 启动开始，每隔 period 触发一次
+    pub fn interval(period: Duration) -> Result<Self> {
+        ensure!(!period.is_zero(), "Interval of scheduled job must be greater than zero");
+        Ok(Self {
+            kind: TriggerKind::Interval(period),
+            missed: MissedRun::default(),
+        })
+    }
+
+    pub fn missed(mut self, missed: MissedRun) -> Self {
+        self.missed = missed;
+        self
+    }
+
+    /// 从 now 开始的第一次触发时间
+    fn first(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.first_after(now, now)
+    }
+
+    /// 严格晚于 from 的第一次计划触发时间，interval 以 anchor 为起点计算周期
+    fn first_after(&self, from: DateTime<Local>, anchor: DateTime<Local>) -> Option<DateTime<Local>> {
+        match &self.kind {
+            TriggerKind::Cron(schedule) => schedule.after(&from).next(),
+            TriggerKind::Interval(period) => {
+                let period = TimeDelta::from_std(*period).ok()?.num_nanoseconds()?;
+                let elapsed = (from - anchor).num_nanoseconds()?.max(0);
+                let offset = (elapsed / period + 1).checked_mul(period)?;
+                anchor.checked_add_signed(TimeDelta::nanoseconds(offset))
+            }
+        }
+    }
+
+    /// 计划在 at 的触发在 now 才被唤醒时是否执行，Skip 只执行没有超出宽限时间的触发
+    fn should_run(&self, at: DateTime<Local>, now: DateTime<Local>) -> bool {
+        match self.missed {
+            MissedRun::Skip => now - at <= MISSED_GRACE,
+            MissedRun::RunOnce => true,
+        }
+    }
+
+    /// 处理完计划在 at 的触发后，根据 now 计算下一次触发时间
+    fn next_run(&self, at: DateTime<Local>, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self.missed {
+            // 跳过所有已经超出宽限时间的触发
+            MissedRun::Skip => self.first_after(at.max(now - MISSED_GRACE), at),
+            // 错过一次或多次时都只在 now 补执行一次，之后的 interval 从 now 重新计算周期
+            MissedRun::RunOnce => self.first_after(at, at).map(|next| next.max(now)),
+        }
+    }
+}
+
+impl TryFrom<&str> for Trigger {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: &str) -> Result<Self> {
+        Self::cron(expression)
+    }
+}
+
+impl TryFrom<Duration> for Trigger {
+    type Error = anyhow::Error;
+
+    fn try_from(period: Duration) -> Result<Self> {
+        Self::interval(period)
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TriggerKind::Cron(schedule) => write!(f, "cron({})", schedule.source()),
+            TriggerKind::Interval(period) => write!(f, "every({period:?})"),
+        }
+    }
+}
+
+/// 插件注册的定时任务
+pub(crate) struct Job {
+    pub(crate) description: Cow<'static, str>,
+    pub(crate) trigger: Trigger,
+    pub(crate) hook: Hook,
+}
+
+impl Job {
+    /// 按照触发时间不断执行任务，同一任务的多次执行不会重叠
+    ///
+    /// stop 变为 true 后不再等待下一次触发，正在执行的任务会先执行完成
+    pub(crate) async fn run(&self, plugin: &str, caller: Arc<dyn Caller>, mut stop: watch::Receiver<bool>) {
+        let mut next = self.trigger.first(Local::now());
+        while let Some(at) = next {
+            // 使用墙上时间计算等待时长，进程被挂起期间错过的触发也能被发现
+            tokio::select! {
+                _ = time::sleep((at - Local::now()).to_std().unwrap_or_default()) => (),
+                _ = stop.wait_for(|stop| *stop) => return,
+            }
+            if self.trigger.should_run(at, Local::now()) {
+                self.execute(plugin, &caller).await;
+            } else {
+                debug!("Skip missed scheduled job {} of {plugin} at {at}", self.description);
+            }
+            next = self.trigger.next_run(at, Local::now());
+        }
+    }
+
+    async fn execute(&self, plugin: &str, caller: &Arc<dyn Caller>) {
+        debug!("Run scheduled job {} of {plugin}", self.description);
+        if let Err(e) = (self.hook)(caller.clone()).await {
+            error!("Failed to run scheduled job {} of {plugin}: {e:?}", self.description);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, day, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn test_trigger_parse() {
+        let trigger = Trigger::cron("0 0 9 * * *").unwrap();
+        assert_eq!(trigger.to_string(), "cron(0 0 9 * * *)");
+        assert_eq!(trigger.missed, MissedRun::Skip);
+        assert!(Trigger::try_from("@daily").is_ok());
+        assert!(Trigger::cron("every day").is_err());
+        let trigger = Trigger::try_from(Duration::from_secs(5)).unwrap();
+        assert_eq!(trigger.to_string(), "every(5s)");
+        assert!(Trigger::interval(Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_cron_next_run() {
+        let at = time(1, 9, 0, 0);
+        for missed in [MissedRun::Skip, MissedRun::RunOnce] {
+            let trigger = Trigger::cron("0 0 9 * * *").unwrap().missed(missed);
+            assert_eq!(trigger.first(time(1, 8, 0, 0)), Some(at));
+            // 按时触发
+            let now = at + TimeDelta::milliseconds(200);
+            assert!(trigger.should_run(at, now));
+            assert_eq!(trigger.next_run(at, now), Some(time(2, 9, 0, 0)));
+        }
+
+        // 晚于宽限时间被唤醒，并且执行期间错过了下一次触发
+        let skip = Trigger::cron("0 0 9 * * *").unwrap();
+        assert!(!skip.should_run(at, time(1, 9, 30, 0)));
+        assert_eq!(skip.next_run(at, time(2, 10, 0, 0)), Some(time(3, 9, 0, 0)));
+
+        let run_once = Trigger::cron("0 0 9 * * *").unwrap().missed(MissedRun::RunOnce);
+        assert!(run_once.should_run(at, time(1, 9, 30, 0)));
+        assert_eq!(run_once.next_run(at, time(2, 10, 0, 0)), Some(time(2, 10, 0, 0)));
+    }
+
+    #[test]
+    fn test_interval_next_run() {
+        let start = time(1, 9, 0, 0);
+        let at = time(1, 9, 0, 10);
+        for missed in [MissedRun::Skip, MissedRun::RunOnce] {
+            let trigger = Trigger::interval(Duration::from_secs(10)).unwrap().missed(missed);
+            assert_eq!(trigger.first(start), Some(at));
+            let now = at + TimeDelta::milliseconds(500);
+            assert!(trigger.should_run(at, now));
+            assert_eq!(trigger.next_run(at, now), Some(time(1, 9, 0, 20)));
+        }
+
+        let skip = Trigger::interval(Duration::from_secs(10)).unwrap();
+        assert!(!skip.should_run(at, time(1, 9, 0, 45)));
+        // 跳过错过的周期，仍然按照原来的周期触发
+        assert_eq!(skip.next_run(at, time(1, 9, 0, 45)), Some(time(1, 9, 0, 50)));
+        // 仍在宽限时间内的触发不会被跳过
+        let now = time(1, 9, 0, 30) + TimeDelta::milliseconds(500);
+        assert_eq!(skip.next_run(at, now), Some(time(1, 9, 0, 30)));
+        assert!(skip.should_run(time(1, 9, 0, 30), now));
+
+        let run_once = Trigger::interval(Duration::from_secs(10))
+            .unwrap()
+            .missed(MissedRun::RunOnce);
+        assert!(run_once.should_run(at, time(1, 9, 0, 45)));
+        assert_eq!(run_once.next_run(at, time(1, 9, 0, 45)), Some(time(1, 9, 0, 45)));
+        // 补执行之后从补执行的时间重新计算周期
+        let at = time(1, 9, 0, 45);
+        assert_eq!(run_once.next_run(at, at), Some(time(1, 9, 0, 55)));
+    }
+}
@@ -1,7 +1,6 @@
-use enum_as_inner::EnumAsInner;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
-use crate::schema::message::MessageContent;
+use crate::{error::ApiError, schema::message::MessageContent};
 /// 发送私聊消息的参数
 #[derive(Debug, Serialize)]
 pub struct SendPrivateMsgParams {
@@ -50,14 +49,14 @@ pub struct SendMsgResult {
 #[derive(Debug, Serialize)]
 pub struct DeleteMsgParams {
     /// 消息 ID
-    message_id: i32,
+    pub message_id: i32,
 }
 
 /// 获取消息的参数
 #[derive(Debug, Serialize)]
 pub struct GetMsgParams {
     /// 消息 ID
-    message_id: i32,
+    pub message_id: i32,
 }
 
 /// 获取消息的响应数据
@@ -74,7 +73,7 @@ pub struct GetMsgResult {
     /// 发送人信息，同 消息事件
     pub sender: serde_json::Value, // 使用 serde_json::Value 作为占位符
     /// 消息内容
-    pub message: String,
+    pub message: MessageContent,
 }
 
 /// 获取合并转发消息的参数
@@ -129,6 +128,10 @@ pub struct SendGroupForwardMsgParams {
     pub messages: MessageContent,
 }
 
+/// 获取登录信息的参数
+#[derive(Debug, Default, Serialize)]
+pub struct GetLoginInfoParams {}
+
 /// 获取登录信息的响应数据
 #[derive(Debug, Deserialize)]
 pub struct GetLoginInfoResult {
@@ -150,32 +153,392 @@ pub struct SendForwardMsgParams {
     pub messages: MessageContent,
 }
 
+/// 获取群列表的参数
+#[derive(Debug, Default, Serialize)]
+pub struct GetGroupListParams {}
+
+/// 获取群信息的参数
 #[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case", tag = "action", content = "params")]
-pub enum RequestParams {
-    GetLoginInfo,
-    SendPrivateMsg(SendPrivateMsgParams),
-    SendGroupMsg(SendGroupMsgParams),
-    SendMsg(SendMsgParams),
-    DeleteMsg(DeleteMsgParams),
-    GetMsg(GetMsgParams),
-    GetForwardMsg(GetForwardMsgParams),
+pub struct GetGroupInfoParams {
+    /// 群号
+    pub group_id: u64,
+    /// 是否不使用缓存（使用缓存可能更新不及时，但响应更快）
+    pub no_cache: bool,
+}
 
-    #[cfg(any(feature = "napcat", feature = "go-cqhttp"))]
-    SendForwardMsg(SendForwardMsgParams),
+/// 群信息
+#[derive(Debug, Deserialize)]
+pub struct GroupInfo {
+    /// 群号
+    pub group_id: u64,
+    /// 群名称
+    pub group_name: String,
+    /// 成员数
+    #[serde(default)]
+    pub member_count: i32,
+    /// 最大成员数（群容量）
+    #[serde(default)]
+    pub max_member_count: i32,
+}
 
-    #[cfg(feature = "napcat")]
-    SetMsgEmojiLike(SetMsgEmojiLikeParams),
+/// 获取群成员信息的参数
+#[derive(Debug, Serialize)]
+pub struct GetGroupMemberInfoParams {
+    /// 群号
+    pub group_id: u64,
+    /// QQ 号
+    pub user_id: u64,
+    /// 是否不使用缓存（使用缓存可能更新不及时，但响应更快）
+    pub no_cache: bool,
+}
 
-    #[cfg(any(feature = "go-cqhttp", feature = "lagrange"))]
-    SetGroupReaction(SetGroupReactionParams),
+/// 获取群成员列表的参数
+#[derive(Debug, Serialize)]
+pub struct GetGroupMemberListParams {
+    /// 群号
+    pub group_id: u64,
+}
 
-    #[cfg(feature = "lagrange")]
-    SendPrivateForwardMsg(SendPrivateForwardMsgParams),
+/// 群成员信息
+#[derive(Debug, Deserialize)]
+pub struct GroupMemberInfo {
+    /// 群号
+    pub group_id: u64,
+    /// QQ 号
+    pub user_id: u64,
+    /// 昵称
+    pub nickname: String,
+    /// 群名片／备注
+    #[serde(default)]
+    pub card: String,
+    /// 性别，male 或 female 或 unknown
+    pub sex: Option<String>,
+    /// 年龄
+    pub age: Option<i32>,
+    /// 地区
+    pub area: Option<String>,
+    /// 加群时间戳
+    #[serde(default)]
+    pub join_time: i64,
+    /// 最后发言时间戳
+    #[serde(default)]
+    pub last_sent_time: i64,
+    /// 成员等级
+    pub level: Option<String>,
+    /// 角色，owner 或 admin 或 member
+    pub role: String,
+    /// 是否不良记录成员
+    #[serde(default)]
+    pub unfriendly: bool,
+    /// 专属头衔
+    pub title: Option<String>,
+    /// 专属头衔过期时间戳
+    pub title_expire_time: Option<i64>,
+    /// 是否允许修改群名片
+    #[serde(default)]
+    pub card_changeable: bool,
+}
+
+impl GroupMemberInfo {
+    /// 优先使用群名片，未设置群名片时使用昵称
+    pub fn display_name(&self) -> &str {
+        if self.card.is_empty() {
+            &self.nickname
+        } else {
+            &self.card
+        }
+    }
+}
+
+/// 获取好友列表的参数
+#[derive(Debug, Default, Serialize)]
+pub struct GetFriendListParams {}
+
+/// 好友信息
+#[derive(Debug, Deserialize)]
+pub struct FriendInfo {
+    /// QQ 号
+    pub user_id: u64,
+    /// 昵称
+    pub nickname: String,
+    /// 备注名
+    #[serde(default)]
+    pub remark: String,
+}
+
+/// 获取陌生人信息的参数
+#[derive(Debug, Serialize)]
+pub struct GetStrangerInfoParams {
+    /// QQ 号
+    pub user_id: u64,
+    /// 是否不使用缓存（使用缓存可能更新不及时，但响应更快）
+    pub no_cache: bool,
+}
+
+/// 陌生人信息
+#[derive(Debug, Deserialize)]
+pub struct StrangerInfo {
+    /// QQ 号
+    pub user_id: u64,
+    /// 昵称
+    pub nickname: String,
+    /// 性别，male 或 female 或 unknown
+    pub sex: Option<String>,
+    /// 年龄
+    pub age: Option<i32>,
+}
+
+/// 获取运行状态的参数
+#[derive(Debug, Default, Serialize)]
+pub struct GetStatusParams {}
+
+/// 运行状态
+#[derive(Debug, Deserialize)]
+pub struct StatusResult {
+    /// 当前 QQ 在线，null 表示无法查询到在线状态
+    pub online: Option<bool>,
+    /// 状态符合预期，意味着各模块正常运行、功能正常，且 QQ 在线
+    pub good: bool,
+}
+
+/// 获取版本信息的参数
+#[derive(Debug, Default, Serialize)]
+pub struct GetVersionInfoParams {}
+
+/// 版本信息
+#[derive(Debug, Deserialize)]
+pub struct VersionInfoResult {
+    /// 应用标识，如 mirai-native
+    pub app_name: String,
+    /// 应用版本，如 1.2.3
+    pub app_version: String,
+    /// OneBot 标准版本，如 v11
+    pub protocol_version: String,
+}
+
+/// 群组踢人的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupKickParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要踢的 QQ 号
+    pub user_id: u64,
+    /// 拒绝此人的加群请求
+    pub reject_add_request: bool,
+}
+
+/// 群组单人禁言的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupBanParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要禁言的 QQ 号
+    pub user_id: u64,
+    /// 禁言时长，单位秒，0 表示取消禁言
+    pub duration: u64,
+}
+
+/// 群组匿名用户禁言的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupAnonymousBanParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要禁言的匿名用户的 flag（需从群消息上报的数据中获得）
+    pub anonymous_flag: String,
+    /// 禁言时长，单位秒，无法取消匿名用户禁言
+    pub duration: u64,
+}
+
+/// 群组全员禁言的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupWholeBanParams {
+    /// 群号
+    pub group_id: u64,
+    /// 是否禁言
+    pub enable: bool,
+}
+
+/// 群组设置管理员的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupAdminParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要设置管理员的 QQ 号
+    pub user_id: u64,
+    /// true 为设置，false 为取消
+    pub enable: bool,
+}
+
+/// 设置群名片（群备注）的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupCardParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要设置的 QQ 号
+    pub user_id: u64,
+    /// 群名片内容，不填或空字符串表示删除群名片
+    pub card: String,
+}
 
+/// 设置群名的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupNameParams {
+    /// 群号
+    pub group_id: u64,
+    /// 新群名
+    pub group_name: String,
+}
+
+/// 退出群组的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupLeaveParams {
+    /// 群号
+    pub group_id: u64,
+    /// 是否解散，如果登录号是群主，则仅在此项为 true 时能够解散
+    pub is_dismiss: bool,
+}
+
+/// 设置群组专属头衔的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupSpecialTitleParams {
+    /// 群号
+    pub group_id: u64,
+    /// 要设置的 QQ 号
+    pub user_id: u64,
+    /// 专属头衔，不填或空字符串表示删除专属头衔
+    pub special_title: String,
+    /// 专属头衔有效期，单位秒，-1 表示永久
+    pub duration: i64,
+}
+
+/// 处理加好友请求的参数
+#[derive(Debug, Serialize)]
+pub struct SetFriendAddRequestParams {
+    /// 加好友请求的 flag（需从上报的数据中获得）
+    pub flag: String,
+    /// 是否同意请求
+    pub approve: bool,
+    /// 添加后的好友备注（仅在同意时有效）
+    pub remark: String,
+}
+
+/// 处理加群请求／邀请的参数
+#[derive(Debug, Serialize)]
+pub struct SetGroupAddRequestParams {
+    /// 加群请求的 flag（需从上报的数据中获得）
+    pub flag: String,
+    /// add 或 invite，请求类型（需要和上报消息中的 sub_type 字段相符）
+    pub sub_type: String,
+    /// 是否同意请求／邀请
+    pub approve: bool,
+    /// 拒绝理由（仅在拒绝时有效）
+    pub reason: String,
+}
+
+/// 快速操作，仅在通过 HTTP POST 上报事件时可用，作为上报请求的响应体返回
+#[derive(Debug, Default, Serialize)]
+pub struct QuickOperation {
+    /// 要回复的内容（消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<MessageContent>,
+    /// 消息内容是否作为纯文本发送（即不解析 CQ 码），只在 reply 字段是字符串时有效（消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_escape: Option<bool>,
+    /// 是否要在回复开头 at 发送者（群消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_sender: Option<bool>,
+    /// 撤回该条消息（群消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete: Option<bool>,
+    /// 把发送者踢出群组（群消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kick: Option<bool>,
+    /// 把发送者禁言 ban_duration 指定时长（群消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban: Option<bool>,
+    /// 禁言时长，单位秒（群消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban_duration: Option<u64>,
+    /// 是否同意请求（请求事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    /// 添加后的好友备注，仅在同意时有效（加好友请求事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    /// 拒绝理由，仅在拒绝时有效（加群请求事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// API 的请求参数，每种请求参数都对应一个确定的响应数据类型
+pub trait Action: Into<RequestParams> + Send {
+    type Output: DeserializeOwned + Send;
+}
+
+/// 声明 API 的名称、请求参数与响应数据类型，新增 API 时只需要在这里添加一行
+macro_rules! actions {
+    ($($(#[$meta:meta])* $action:ident($params:ty) => $output:ty,)*) => {
+        #[derive(Debug, Serialize)]
+        #[serde(rename_all = "snake_case", tag = "action", content = "params")]
+        pub enum RequestParams {
+            $($(#[$meta])* $action($params),)*
+        }
+
+        $(
+            $(#[$meta])*
+            impl From<$params> for RequestParams {
+                fn from(params: $params) -> Self {
+                    RequestParams::$action(params)
+                }
+            }
+
+            $(#[$meta])*
+            impl Action for $params {
+                type Output = $output;
+            }
+        )*
+    };
+}
+
+actions! {
+    GetLoginInfo(GetLoginInfoParams) => GetLoginInfoResult,
+    SendPrivateMsg(SendPrivateMsgParams) => SendMsgResult,
+    SendGroupMsg(SendGroupMsgParams) => SendMsgResult,
+    SendMsg(SendMsgParams) => SendMsgResult,
+    DeleteMsg(DeleteMsgParams) => serde_json::Value,
+    GetMsg(GetMsgParams) => GetMsgResult,
+    GetForwardMsg(GetForwardMsgParams) => GetForwardMsgResult,
+    GetGroupList(GetGroupListParams) => Vec<GroupInfo>,
+    GetGroupInfo(GetGroupInfoParams) => GroupInfo,
+    GetGroupMemberInfo(GetGroupMemberInfoParams) => GroupMemberInfo,
+    GetGroupMemberList(GetGroupMemberListParams) => Vec<GroupMemberInfo>,
+    GetFriendList(GetFriendListParams) => Vec<FriendInfo>,
+    GetStrangerInfo(GetStrangerInfoParams) => StrangerInfo,
+    GetStatus(GetStatusParams) => StatusResult,
+    GetVersionInfo(GetVersionInfoParams) => VersionInfoResult,
+    SetGroupKick(SetGroupKickParams) => serde_json::Value,
+    SetGroupBan(SetGroupBanParams) => serde_json::Value,
+    SetGroupAnonymousBan(SetGroupAnonymousBanParams) => serde_json::Value,
+    SetGroupWholeBan(SetGroupWholeBanParams) => serde_json::Value,
+    SetGroupAdmin(SetGroupAdminParams) => serde_json::Value,
+    SetGroupCard(SetGroupCardParams) => serde_json::Value,
+    SetGroupName(SetGroupNameParams) => serde_json::Value,
+    SetGroupLeave(SetGroupLeaveParams) => serde_json::Value,
+    SetGroupSpecialTitle(SetGroupSpecialTitleParams) => serde_json::Value,
+    SetFriendAddRequest(SetFriendAddRequestParams) => serde_json::Value,
+    SetGroupAddRequest(SetGroupAddRequestParams) => serde_json::Value,
+    #[cfg(any(feature = "napcat", feature = "go-cqhttp"))]
+    SendForwardMsg(SendForwardMsgParams) => SendMsgResult,
+    #[cfg(feature = "napcat")]
+    SetMsgEmojiLike(SetMsgEmojiLikeParams) => serde_json::Value,
+    #[cfg(any(feature = "go-cqhttp", feature = "lagrange"))]
+    SetGroupReaction(SetGroupReactionParams) => serde_json::Value,
+    #[cfg(feature = "lagrange")]
+    SendPrivateForwardMsg(SendPrivateForwardMsgParams) => SendMsgResult,
     #[cfg(feature = "lagrange")]
-    SendGroupForwardMsg(SendGroupForwardMsgParams),
+    SendGroupForwardMsg(SendGroupForwardMsgParams) => SendMsgResult,
 }
+
 #[derive(Debug, Serialize)]
 pub struct ApiRequest {
     echo: u64,
@@ -184,11 +547,11 @@ pub struct ApiRequest {
 }
 
 impl ApiRequest {
-    pub fn new(params: RequestParams) -> Self {
+    pub fn new(params: impl Into<RequestParams>) -> Self {
         Self {
             // 发现这里如果生成的 u64 过长，接口返回的 echo 可能丢失精度，因此减小一些
             echo: rand::random::<u64>() >> 16,
-            params,
+            params: params.into(),
         }
     }
 
@@ -197,26 +560,44 @@ impl ApiRequest {
     }
 }
 
-#[derive(Debug, Deserialize, EnumAsInner)]
-#[serde(untagged)]
-pub enum ResponseBody {
-    GetLoginInfo(GetLoginInfoResult),
-    SendMsg(SendMsgResult),
-    GetMsg(GetMsgResult),
-    GetForwardMsg(GetForwardMsgResult),
-    Fallback(serde_json::Value),
-}
-
 #[derive(Debug, Deserialize)]
 pub struct ApiResponse {
     echo: u64,
-    pub data: ResponseBody,
+    /// 执行状态，ok 表示成功，async 表示已提交异步处理，failed 表示失败
+    pub status: String,
+    /// 返回码，0 表示成功，1 表示已提交异步处理，其余均表示失败
+    pub retcode: i64,
+    /// 错误信息，go-cqhttp 中该字段名为 msg
+    #[serde(default, alias = "msg")]
+    pub message: Option<String>,
+    /// 对错误的详细解释
+    #[serde(default)]
+    pub wording: Option<String>,
+    /// 响应数据，在调用方按照请求对应的类型解析
+    #[serde(default)]
+    pub data: serde_json::Value,
 }
 
 impl ApiResponse {
     pub fn echo(&self) -> u64 {
         self.echo
     }
+
+    /// 检查执行状态，成功时返回响应数据
+    pub fn into_data(self) -> Result<serde_json::Value, ApiError> {
+        if self.status == "failed" || !matches!(self.retcode, 0 | 1) {
+            let message = self
+                .message
+                .filter(|message| !message.is_empty())
+                .or(self.wording)
+                .unwrap_or_default();
+            return Err(ApiError::Failed {
+                retcode: self.retcode,
+                message,
+            });
+        }
+        Ok(self.data)
+    }
 }
 
 #[cfg(test)]
@@ -230,9 +611,32 @@ mod tests {
             message: MessageContent::Text("Hello, world!".to_string()),
             auto_escape: true,
         }));
+        // echo 是随机生成的，只比较其余字段
+        let mut value = serde_json::to_value(&send_private_msg).unwrap();
+        assert_eq!(value["echo"], send_private_msg.echo());
+        value.as_object_mut().unwrap().remove("echo");
         assert_eq!(
-            serde_json::to_string(&send_private_msg).unwrap(),
-            r#"{"action":"send_private_msg","params":{"user_id":10000,"message":"Hello, world!","auto_escape":false}}"#
+            value,
+            serde_json::from_str::<serde_json::Value>(
+                r#"{"action":"send_private_msg","params":{"user_id":10000,"message":"Hello, world!","auto_escape":true}}"#
+            )
+            .unwrap()
         );
     }
+
+    #[test]
+    fn test_api_response_failed() {
+        let response: ApiResponse = serde_json::from_str(
+            r#"{"status":"failed","retcode":100,"data":null,"message":"","wording":"bot muted","echo":1}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            response.into_data(),
+            Err(ApiError::Failed { retcode: 100, message }) if message == "bot muted"
+        ));
+        let response: ApiResponse =
+            serde_json::from_str(r#"{"status":"ok","retcode":0,"data":{"message_id":1},"echo":1}"#).unwrap();
+        let data = response.into_data().unwrap();
+        assert_eq!(serde_json::from_value::<SendMsgResult>(data).unwrap().message_id, 1);
+    }
 }
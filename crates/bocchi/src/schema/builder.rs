@@ -0,0 +1,267 @@
+use std::{collections::HashMap, path::Path};
+
+use bytes::Bytes;
+
+use crate::{
+    media::{self, Media},
+    schema::{MessageContent, MessageSegment},
+};
+
+/// 媒体内容在发送前使用的占位 file 参数
+const MEDIA_PLACEHOLDER: &str = "bocchi-media://";
+
+/// 链式构造消息段，避免手动填写消息段中大量的可选字段
+///
+/// 通过 Media 添加的图片、语音与视频会在发送时按照 MediaTransport 转换
+#[derive(Debug, Clone, Default)]
+pub struct MessageBuilder {
+    segments: Vec<MessageSegment>,
+    media: HashMap<String, Media>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn segment(mut self, segment: MessageSegment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.segment(MessageSegment::Text { text: text.into() })
+    }
+
+    /// @某人，传入 `"all"` 表示@全体成员
+    pub fn at(self, user_id: impl ToString) -> Self {
+        self.segment(MessageSegment::At {
+            qq: user_id.to_string(),
+        })
+    }
+
+    pub fn reply(self, message_id: impl ToString) -> Self {
+        self.segment(MessageSegment::Reply {
+            id: message_id.to_string(),
+        })
+    }
+
+    pub fn face(self, id: impl ToString) -> Self {
+        self.segment(MessageSegment::Face { id: id.to_string() })
+    }
+
+    /// 发送本地图片
+    pub fn image_file(self, path: impl AsRef<Path>) -> Self {
+        self.image_media(path.as_ref())
+    }
+
+    pub fn image_url(self, url: impl Into<String>) -> Self {
+        self.image(url.into())
+    }
+
+    /// 发送内存中的图片内容
+    pub fn image_bytes(self, bytes: impl Into<Bytes>) -> Self {
+        self.image_media(bytes.into())
+    }
+
+    pub fn image_media(mut self, media: impl Into<Media>) -> Self {
+        let file = self.pending(media.into());
+        self.image(file)
+    }
+
+    /// 发送语音，file 支持本地路径（file://）、网络 URL 与 base64://
+    pub fn record(self, file: impl Into<String>) -> Self {
+        self.segment(MessageSegment::Record {
+            file: file.into(),
+            magic: None,
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        })
+    }
+
+    pub fn record_media(mut self, media: impl Into<Media>) -> Self {
+        let file = self.pending(media.into());
+        self.record(file)
+    }
+
+    /// 发送短视频，file 支持本地路径（file://）、网络 URL 与 base64://
+    pub fn video(self, file: impl Into<String>) -> Self {
+        self.segment(MessageSegment::Video {
+            file: file.into(),
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        })
+    }
+
+    pub fn video_media(mut self, media: impl Into<Media>) -> Self {
+        let file = self.pending(media.into());
+        self.video(file)
+    }
+
+    /// 合并转发节点，仅用于发送合并转发消息
+    pub fn node(
+        mut self,
+        user_id: impl ToString,
+        nickname: impl Into<String>,
+        content: impl Into<MessageBuilder>,
+    ) -> Self {
+        let content = content.into();
+        self.media.extend(content.media);
+        self.segment(MessageSegment::Node {
+            id: None,
+            user_id: Some(user_id.to_string()),
+            nickname: Some(nickname.into()),
+            content: Some(MessageContent::Segment(content.segments)),
+        })
+    }
+
+    /// 追加另一条消息的全部内容
+    pub fn append(mut self, other: impl Into<MessageBuilder>) -> Self {
+        let other = other.into();
+        self.segments.extend(other.segments);
+        self.media.extend(other.media);
+        self
+    }
+
+    /// 设置上一个图片、语音或视频是否使用已缓存的文件
+    pub fn cache(mut self, enable: bool) -> Self {
+        if let Some((cache, _, _)) = self.last_media() {
+            *cache = Some(enable);
+        }
+        self
+    }
+
+    /// 设置上一个图片、语音或视频是否通过代理下载
+    pub fn proxy(mut self, enable: bool) -> Self {
+        if let Some((_, proxy, _)) = self.last_media() {
+            *proxy = Some(enable);
+        }
+        self
+    }
+
+    /// 设置上一个图片、语音或视频的下载超时时间，单位秒
+    pub fn timeout(mut self, seconds: u64) -> Self {
+        if let Some((_, _, timeout)) = self.last_media() {
+            *timeout = Some(seconds);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// 直接构造消息段，待发送的媒体内容会使用 file:// 或 base64:// 内联，不经过 MediaTransport
+    pub fn build(self) -> Vec<MessageSegment> {
+        let Self { mut segments, media } = self;
+        if !media.is_empty() {
+            media::replace_files(&mut segments, &mut |file| {
+                if let Some(media) = media.get(file.as_str()) {
+                    *file = media.inline();
+                }
+            });
+        }
+        segments
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<MessageSegment>, HashMap<String, Media>) {
+        (self.segments, self.media)
+    }
+
+    /// 登记待发送的媒体内容，返回占位用的 file 参数
+    fn pending(&mut self, media: Media) -> String {
+        let placeholder = format!("{MEDIA_PLACEHOLDER}{}", media::token());
+        self.media.insert(placeholder.clone(), media);
+        placeholder
+    }
+
+    fn image(self, file: String) -> Self {
+        self.segment(MessageSegment::Image {
+            file,
+            r#type: None,
+            url: None,
+            cache: None,
+            proxy: None,
+            timeout: None,
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn last_media(&mut self) -> Option<(&mut Option<bool>, &mut Option<bool>, &mut Option<u64>)> {
+        match self.segments.last_mut()? {
+            MessageSegment::Image {
+                cache, proxy, timeout, ..
+            }
+            | MessageSegment::Record {
+                cache, proxy, timeout, ..
+            }
+            | MessageSegment::Video {
+                cache, proxy, timeout, ..
+            } => Some((cache, proxy, timeout)),
+            _ => None,
+        }
+    }
+}
+
+impl From<MessageBuilder> for MessageContent {
+    fn from(builder: MessageBuilder) -> Self {
+        MessageContent::Segment(builder.build())
+    }
+}
+
+impl From<MessageBuilder> for Vec<MessageSegment> {
+    fn from(builder: MessageBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// 字符串格式的消息总是作为纯文本处理，需要解析 CQ 码时使用 `cq_code::parse` 得到消息段
+impl From<MessageContent> for MessageBuilder {
+    fn from(content: MessageContent) -> Self {
+        match content {
+            MessageContent::Text(text) => Self::new().text(text),
+            MessageContent::Segment(segments) => segments.into(),
+        }
+    }
+}
+
+impl From<Vec<MessageSegment>> for MessageBuilder {
+    fn from(segments: Vec<MessageSegment>) -> Self {
+        Self {
+            segments,
+            media: HashMap::new(),
+        }
+    }
+}
+
+impl From<String> for MessageBuilder {
+    fn from(text: String) -> Self {
+        Self::new().text(text)
+    }
+}
+
+impl From<&str> for MessageBuilder {
+    fn from(text: &str) -> Self {
+        Self::new().text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_not_cq_code() {
+        let builder = MessageBuilder::from(MessageContent::Text("[CQ:at,qq=all] hello".to_string()));
+        assert_eq!(
+            builder.build(),
+            [MessageSegment::Text {
+                text: "[CQ:at,qq=all] hello".to_string()
+            }]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! CQ 码的解析与序列化，用于兼容使用字符串格式上报消息的 OneBot 实现（如 go-cqhttp）
+use std::fmt::Write;
+
+use serde_json::{Map, Value, json};
+
+use crate::schema::MessageSegment;
+
+/// 将包含 CQ 码的字符串解析为消息段，无法识别的 CQ 码会保留为纯文本
+pub fn parse(message: &str) -> Vec<MessageSegment> {
+    let mut segments = Vec::new();
+    let mut rest = message;
+    while let Some(start) = rest.find("[CQ:") {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        push_text(&mut segments, &rest[..start]);
+        let code = &rest[start..=start + len];
+        match parse_code(&code[4..code.len() - 1]) {
+            Some(segment) => segments.push(segment),
+            None => push_text(&mut segments, code),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    push_text(&mut segments, rest);
+    segments
+}
+
+/// 将消息段序列化为包含 CQ 码的字符串
+pub fn serialize(segments: &[MessageSegment]) -> String {
+    let mut message = String::new();
+    for segment in segments {
+        if let MessageSegment::Text { text } = segment {
+            message.push_str(&escape(text, false));
+            continue;
+        }
+        let Ok(Value::Object(mut value)) = serde_json::to_value(segment) else {
+            continue;
+        };
+        let Some(Value::String(r#type)) = value.remove("type") else {
+            continue;
+        };
+        let _ = write!(message, "[CQ:{type}");
+        if let Some(Value::Object(data)) = value.remove("data") {
+            for (key, value) in data {
+                let value = match value {
+                    Value::Null => continue,
+                    Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                let _ = write!(message, ",{key}={}", escape(&value, true));
+            }
+        }
+        message.push(']');
+    }
+    message
+}
+
+/// 转义纯文本或 CQ 码参数中的特殊字符，参数中的逗号也需要转义
+pub fn escape(text: &str, in_param: bool) -> String {
+    let text = text.replace('&', "&amp;").replace('[', "&#91;").replace(']', "&#93;");
+    if in_param { text.replace(',', "&#44;") } else { text }
+}
+
+pub fn unescape(text: &str) -> String {
+    // &amp; 需要最后处理，避免 &amp;#91; 被错误地还原为 [
+    text.replace("&#44;", ",")
+        .replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&amp;", "&")
+}
+
+fn push_text(segments: &mut Vec<MessageSegment>, text: &str) {
+    if !text.is_empty() {
+        segments.push(MessageSegment::Text { text: unescape(text) });
+    }
+}
+
+/// 解析形如 `image,file=123.jpg` 的 CQ 码内容
+fn parse_code(code: &str) -> Option<MessageSegment> {
+    let mut parts = code.split(',');
+    let r#type = parts.next()?;
+    let mut data = Map::new();
+    for part in parts {
+        let (key, value) = part.split_once('=')?;
+        data.insert(key.to_owned(), param_value(key, unescape(value)));
+    }
+    if data.is_empty()
+        && let Ok(segment) = serde_json::from_value(json!({ "type": r#type }))
+    {
+        return Some(segment);
+    }
+    serde_json::from_value(json!({ "type": r#type, "data": data })).ok()
+}
+
+/// CQ 码的参数均为字符串，需要按照 MessageSegment 中的字段类型转换
+fn param_value(key: &str, value: String) -> Value {
+    match key {
+        "cache" | "proxy" | "magic" | "ignore" => Value::Bool(matches!(value.as_str(), "1" | "true" | "yes")),
+        "timeout" => value.parse::<u64>().map_or(Value::String(value), Value::from),
+        _ => Value::String(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cq_code() {
+        let message = "看看&#91;图&#93; [CQ:face,id=178][CQ:image,file=a&#44;b.jpg,cache=0]&amp;[CQ:unknown,x=1]";
+        let segments = parse(message);
+        assert_eq!(
+            segments,
+            vec![
+                MessageSegment::Text {
+                    text: "看看[图] ".to_owned()
+                },
+                MessageSegment::Face { id: "178".to_owned() },
+                MessageSegment::Image {
+                    file: "a,b.jpg".to_owned(),
+                    r#type: None,
+                    url: None,
+                    cache: Some(false),
+                    proxy: None,
+                    timeout: None,
+                },
+                MessageSegment::Text { text: "&".to_owned() },
+                MessageSegment::Text {
+                    text: "[CQ:unknown,x=1]".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            serialize(&segments[..4]),
+            "看看&#91;图&#93; [CQ:face,id=178][CQ:image,cache=false,file=a&#44;b.jpg]&amp;"
+        );
+        assert_eq!(parse("[CQ:rps]"), vec![MessageSegment::Rps]);
+    }
+}
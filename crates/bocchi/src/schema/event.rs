@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
 
 use crate::schema::{MessageContent, MessageSegment};
@@ -18,17 +18,37 @@ pub struct Sender {
     pub title: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+/// 群成员的身份，按照权限从低到高排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Sender {
+    /// 发送者在群中的身份，私聊消息没有身份
+    pub fn group_role(&self) -> Option<Role> {
+        match self.role.as_deref()? {
+            "owner" => Some(Role::Owner),
+            "admin" => Some(Role::Admin),
+            "member" => Some(Role::Member),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Anonymous {
     pub id: i64,
     pub name: String,
     pub flag: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct PrivateMessage {
     pub time: i64,
-    pub self_id: i64,
+    pub self_id: u64,
     pub post_type: String,
     pub message_type: String,
     pub sub_type: String,
@@ -38,9 +58,16 @@ pub struct PrivateMessage {
     pub raw_message: String,
     pub font: i32,
     pub sender: Sender,
+    /// 私聊消息总是对 bot 说的
+    #[serde(skip, default = "always_to_me")]
+    pub to_me: bool,
 }
 
-#[derive(Deserialize, Debug)]
+fn always_to_me() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct GroupMessage {
     pub time: i64,
     pub self_id: u64,
@@ -55,9 +82,12 @@ pub struct GroupMessage {
     pub raw_message: String,
     pub font: i32,
     pub sender: Sender,
+    /// 消息是否 @ 了 bot、回复了 bot 的消息或以 bot 的昵称开头
+    #[serde(skip)]
+    pub to_me: bool,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct LifeCycle {
     pub time: i64,
     pub self_id: u64,
@@ -66,7 +96,7 @@ pub struct LifeCycle {
     pub sub_type: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct HeartBeat {
     pub time: i64,
     pub self_id: u64,
@@ -76,16 +106,273 @@ pub struct HeartBeat {
     pub interval: i64,
 }
 
-#[derive(Deserialize, Debug)]
+/// 群文件上传中的文件信息
+#[derive(Deserialize, Debug, Clone)]
+pub struct UploadFile {
+    /// 文件 ID
+    pub id: String,
+    /// 文件名
+    pub name: String,
+    /// 文件大小（字节数）
+    pub size: i64,
+    /// busid（目前不清楚有什么作用）
+    pub busid: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupUpload {
+    pub group_id: u64,
+    pub user_id: u64,
+    pub file: UploadFile,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupAdmin {
+    /// set、unset，分别表示设置和取消管理员
+    pub sub_type: String,
+    pub group_id: u64,
+    pub user_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupDecrease {
+    /// leave、kick、kick_me，分别表示主动退群、成员被踢、登录号被踢
+    pub sub_type: String,
+    pub group_id: u64,
+    /// 操作者 QQ 号（如果是主动退群，则和 user_id 相同）
+    pub operator_id: u64,
+    pub user_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupIncrease {
+    /// approve、invite，分别表示管理员已同意入群、管理员邀请入群
+    pub sub_type: String,
+    pub group_id: u64,
+    pub operator_id: u64,
+    pub user_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupBan {
+    /// ban、lift_ban，分别表示禁言、解除禁言
+    pub sub_type: String,
+    pub group_id: u64,
+    pub operator_id: u64,
+    pub user_id: u64,
+    /// 禁言时长，单位秒
+    pub duration: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FriendAdd {
+    pub user_id: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupRecall {
+    pub group_id: u64,
+    pub user_id: u64,
+    pub operator_id: u64,
+    pub message_id: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FriendRecall {
+    pub user_id: u64,
+    pub message_id: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "sub_type", rename_all = "snake_case")]
+pub enum Notify {
+    /// 戳一戳，私聊戳一戳时没有 group_id
+    Poke {
+        group_id: Option<u64>,
+        user_id: u64,
+        target_id: u64,
+    },
+    /// 群红包运气王
+    LuckyKing {
+        group_id: u64,
+        user_id: u64,
+        target_id: u64,
+    },
+    /// 群成员荣誉变更，honor_type 为 talkative、performer、emotion 之一
+    Honor {
+        group_id: u64,
+        user_id: u64,
+        honor_type: String,
+    },
+    /// 无法识别的 notify 通知，例如 NapCat 的 input_status，包含 sub_type 在内的所有字段
+    #[serde(untagged)]
+    Other(serde_json::Value),
+}
+
+/// 无法识别的通知，例如 group_card、essence 等 OneBot 实现扩展的通知
+#[derive(Deserialize, Debug, Clone)]
+pub struct OtherNotice {
+    pub notice_type: String,
+    /// 除 notice_type 与公共字段外的其余字段
+    #[serde(flatten)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "notice_type", rename_all = "snake_case")]
+pub enum NoticeKind {
+    GroupUpload(GroupUpload),
+    GroupAdmin(GroupAdmin),
+    GroupDecrease(GroupDecrease),
+    GroupIncrease(GroupIncrease),
+    GroupBan(GroupBan),
+    FriendAdd(FriendAdd),
+    GroupRecall(GroupRecall),
+    FriendRecall(FriendRecall),
+    Notify(Notify),
+    /// 要求存在 notice_type 字段，避免其它类型的事件被当作通知
+    #[serde(untagged)]
+    Other(OtherNotice),
+}
+
+/// 通知的类型，notify 类通知按照 sub_type 细分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoticeType {
+    GroupUpload,
+    GroupAdmin,
+    GroupDecrease,
+    GroupIncrease,
+    GroupBan,
+    FriendAdd,
+    GroupRecall,
+    FriendRecall,
+    Poke,
+    LuckyKing,
+    Honor,
+    /// 无法识别的通知
+    Other,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Notice {
+    pub time: i64,
+    pub self_id: u64,
+    pub post_type: String,
+    #[serde(flatten)]
+    pub kind: NoticeKind,
+}
+
+impl Notice {
+    pub fn notice_type(&self) -> NoticeType {
+        match &self.kind {
+            NoticeKind::GroupUpload(_) => NoticeType::GroupUpload,
+            NoticeKind::GroupAdmin(_) => NoticeType::GroupAdmin,
+            NoticeKind::GroupDecrease(_) => NoticeType::GroupDecrease,
+            NoticeKind::GroupIncrease(_) => NoticeType::GroupIncrease,
+            NoticeKind::GroupBan(_) => NoticeType::GroupBan,
+            NoticeKind::FriendAdd(_) => NoticeType::FriendAdd,
+            NoticeKind::GroupRecall(_) => NoticeType::GroupRecall,
+            NoticeKind::FriendRecall(_) => NoticeType::FriendRecall,
+            NoticeKind::Notify(Notify::Poke { .. }) => NoticeType::Poke,
+            NoticeKind::Notify(Notify::LuckyKing { .. }) => NoticeType::LuckyKing,
+            NoticeKind::Notify(Notify::Honor { .. }) => NoticeType::Honor,
+            NoticeKind::Notify(Notify::Other(_)) | NoticeKind::Other(_) => NoticeType::Other,
+        }
+    }
+
+    /// 通知发生的群，好友相关的通知返回 None
+    pub fn group_id(&self) -> Option<u64> {
+        match &self.kind {
+            NoticeKind::GroupUpload(GroupUpload { group_id, .. })
+            | NoticeKind::GroupAdmin(GroupAdmin { group_id, .. })
+            | NoticeKind::GroupDecrease(GroupDecrease { group_id, .. })
+            | NoticeKind::GroupIncrease(GroupIncrease { group_id, .. })
+            | NoticeKind::GroupBan(GroupBan { group_id, .. })
+            | NoticeKind::GroupRecall(GroupRecall { group_id, .. })
+            | NoticeKind::Notify(Notify::LuckyKing { group_id, .. } | Notify::Honor { group_id, .. }) => {
+                Some(*group_id)
+            }
+            NoticeKind::Notify(Notify::Poke { group_id, .. }) => *group_id,
+            NoticeKind::FriendAdd(_) | NoticeKind::FriendRecall(_) => None,
+            NoticeKind::Notify(Notify::Other(data)) => data.get("group_id")?.as_u64(),
+            NoticeKind::Other(OtherNotice { data, .. }) => data.get("group_id")?.as_u64(),
+        }
+    }
+
+    /// 通知涉及的用户，例如入群的成员、被撤回消息的发送者、发起戳一戳的用户
+    pub fn user_id(&self) -> Option<u64> {
+        match &self.kind {
+            NoticeKind::GroupUpload(GroupUpload { user_id, .. })
+            | NoticeKind::GroupAdmin(GroupAdmin { user_id, .. })
+            | NoticeKind::GroupDecrease(GroupDecrease { user_id, .. })
+            | NoticeKind::GroupIncrease(GroupIncrease { user_id, .. })
+            | NoticeKind::GroupBan(GroupBan { user_id, .. })
+            | NoticeKind::FriendAdd(FriendAdd { user_id })
+            | NoticeKind::GroupRecall(GroupRecall { user_id, .. })
+            | NoticeKind::FriendRecall(FriendRecall { user_id, .. })
+            | NoticeKind::Notify(
+                Notify::Poke { user_id, .. } | Notify::LuckyKing { user_id, .. } | Notify::Honor { user_id, .. },
+            ) => Some(*user_id),
+            NoticeKind::Notify(Notify::Other(data)) => data.get("user_id")?.as_u64(),
+            NoticeKind::Other(OtherNotice { data, .. }) => data.get("user_id")?.as_u64(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FriendRequest {
+    pub time: i64,
+    pub self_id: u64,
+    pub post_type: String,
+    pub request_type: String,
+    pub user_id: u64,
+    /// 验证信息
+    pub comment: String,
+    /// 请求 flag，在调用处理请求的 API 时需要传入
+    pub flag: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GroupRequest {
+    pub time: i64,
+    pub self_id: u64,
+    pub post_type: String,
+    pub request_type: String,
+    /// add、invite，分别表示加群请求、邀请登录号入群
+    pub sub_type: String,
+    pub group_id: u64,
+    pub user_id: u64,
+    /// 验证信息
+    pub comment: String,
+    /// 请求 flag，在调用处理请求的 API 时需要传入
+    pub flag: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum Event {
     GroupMessage(GroupMessage),
     PrivateMessage(PrivateMessage),
     LifeCycle(LifeCycle),
     HeartBeat(HeartBeat),
+    Notice(Notice),
+    // GroupRequest 的字段是 FriendRequest 的超集，需要放在前面
+    GroupRequest(GroupRequest),
+    FriendRequest(FriendRequest),
 }
 
 impl<'a> Event {
+    pub fn try_notice(&self) -> Result<&Notice> {
+        match self {
+            Self::Notice(notice) => Ok(notice),
+            _ => bail!("Event::try_notice() called on non-notice event"),
+        }
+    }
+
+    pub fn notice(&self) -> &Notice {
+        self.try_notice().unwrap()
+    }
+
     pub fn try_sender(&self) -> Result<&Sender> {
         match self {
             Self::GroupMessage(GroupMessage { sender, .. }) | Self::PrivateMessage(PrivateMessage { sender, .. }) => {
@@ -117,14 +404,24 @@ impl<'a> Event {
             Self::GroupMessage(GroupMessage { user_id, .. }) | Self::PrivateMessage(PrivateMessage { user_id, .. }) => {
                 Ok(*user_id)
             }
+            Self::Notice(notice) => notice
+                .user_id()
+                .ok_or_else(|| anyhow!("Event::try_user_id() called on notice event without user_id")),
+            Self::GroupRequest(GroupRequest { user_id, .. }) | Self::FriendRequest(FriendRequest { user_id, .. }) => {
+                Ok(*user_id)
+            }
             _ => bail!("Event::try_user_id() called on non-message event"),
         }
     }
 
-    /// 仅在私聊消息中返回 Ok(user_id)，否则返回 Err
+    /// 仅在私聊消息与好友相关的通知中返回 Ok(user_id)，否则返回 Err
     pub fn try_private_user_id(&self) -> Result<u64> {
         match self {
             Self::PrivateMessage(PrivateMessage { user_id, .. }) => Ok(*user_id),
+            Self::Notice(notice) if notice.group_id().is_none() => notice
+                .user_id()
+                .ok_or_else(|| anyhow!("Event::try_private_user_id() called on notice event without user_id")),
+            Self::FriendRequest(FriendRequest { user_id, .. }) => Ok(*user_id),
             _ => bail!("Event::try_private_user_id() called on non-private-message event"),
         }
     }
@@ -135,7 +432,12 @@ impl<'a> Event {
 
     pub fn try_group_id(&self) -> Result<u64> {
         match self {
-            Self::GroupMessage(GroupMessage { group_id, .. }) => Ok(*group_id),
+            Self::GroupMessage(GroupMessage { group_id, .. }) | Self::GroupRequest(GroupRequest { group_id, .. }) => {
+                Ok(*group_id)
+            }
+            Self::Notice(notice) => notice
+                .group_id()
+                .ok_or_else(|| anyhow!("Event::try_group_id() called on non-group notice event")),
             _ => bail!("Event::try_group_id() called on non-group-message event"),
         }
     }
@@ -170,24 +472,322 @@ impl<'a> Event {
     }
 
     pub fn try_plain_text(&self) -> Result<Cow<'_, str>> {
-        let msg = self.try_message()?;
-        match msg {
-            // 这里作为通用实现可能是错误的，因为框架可能会发送包含 CQ 码的纯文本而非结构化数据，
-            // 如“[CQ:face,id=178] 看看我刚拍的照片 [CQ:image,file=123.jpg]”
-            // 但实现 CQ 码解析会很麻烦，而且使用 napcat 时并未出现这种情况，所以先这样用着
-            MessageContent::Text(text) => Ok(Cow::Borrowed(text)),
-            MessageContent::Segment(segment) => Ok(segment
-                .iter()
-                .filter_map(|seg| match seg {
-                    MessageSegment::Text { text } => Some(text.as_str()),
-                    _ => None,
-                })
-                .collect::<String>()
-                .into()),
-        }
+        // 字符串格式的消息会按照 CQ 码解析，只保留其中的纯文本
+        Ok(self.try_message()?.plain_text().into())
     }
 
     pub fn plain_text(&'a self) -> Cow<'a, str> {
         self.try_plain_text().unwrap()
     }
+
+    /// 将字符串格式的消息解析为消息段，使不同 OneBot 实现上报的消息格式保持一致
+    pub fn normalize(&mut self) {
+        if let Self::GroupMessage(GroupMessage { message, .. }) | Self::PrivateMessage(PrivateMessage { message, .. }) =
+            self
+        {
+            message.normalize();
+        }
+    }
+
+    /// 收到事件的 bot 账号
+    pub fn self_id(&self) -> u64 {
+        match self {
+            Self::GroupMessage(GroupMessage { self_id, .. })
+            | Self::PrivateMessage(PrivateMessage { self_id, .. })
+            | Self::LifeCycle(LifeCycle { self_id, .. })
+            | Self::HeartBeat(HeartBeat { self_id, .. })
+            | Self::Notice(Notice { self_id, .. })
+            | Self::GroupRequest(GroupRequest { self_id, .. })
+            | Self::FriendRequest(FriendRequest { self_id, .. }) => *self_id,
+        }
+    }
+
+    /// 消息是否是对 bot 说的，非消息事件总是返回 false
+    pub fn is_to_me(&self) -> bool {
+        match self {
+            Self::GroupMessage(GroupMessage { to_me, .. }) | Self::PrivateMessage(PrivateMessage { to_me, .. }) => {
+                *to_me
+            }
+            _ => false,
+        }
+    }
+
+    /// 识别消息开头或结尾的 @bot 与开头的昵称，标记 to_me 并将其从消息中去除
+    ///
+    /// 需要在 normalize 之后调用，回复 bot 的消息需要调用 API 才能识别，不在这里处理
+    pub(crate) fn strip_to_me(&mut self, nicknames: &[String]) {
+        let (self_id, message, to_me) = match self {
+            Self::GroupMessage(GroupMessage {
+                self_id,
+                message,
+                to_me,
+                ..
+            })
+            | Self::PrivateMessage(PrivateMessage {
+                self_id,
+                message,
+                to_me,
+                ..
+            }) => (*self_id, message, to_me),
+            _ => return,
+        };
+        let MessageContent::Segment(segments) = message else {
+            return;
+        };
+        let self_id = self_id.to_string();
+        let is_at_me = |segment: &MessageSegment| matches!(segment, MessageSegment::At { qq } if *qq == self_id);
+        // 回复消息时 QQ 会在开头放置 Reply 消息段，@ 与昵称出现在它之后
+        let start = segments
+            .iter()
+            .take_while(|segment| matches!(segment, MessageSegment::Reply { .. }))
+            .count();
+        if segments.get(start).is_some_and(is_at_me) {
+            segments.remove(start);
+            trim_text(segments, start, str::trim_start);
+            *to_me = true;
+            return;
+        }
+        if segments.len() > start + 1 && segments.last().is_some_and(is_at_me) {
+            segments.pop();
+            trim_text(segments, segments.len() - 1, str::trim_end);
+            *to_me = true;
+            return;
+        }
+        let Some(MessageSegment::Text { text }) = segments.get_mut(start) else {
+            return;
+        };
+        let trimmed = text.trim_start();
+        // 昵称之后紧跟字母或数字时视为另一个单词，例如昵称为 bocchi 时不匹配 bocchiXYZ
+        let Some(rest) = nicknames
+            .iter()
+            .filter_map(|nickname| trimmed.strip_prefix(nickname.as_str()))
+            .find(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric()))
+        else {
+            return;
+        };
+        *text = rest
+            .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '，' | ':' | '：'))
+            .to_owned();
+        if text.is_empty() {
+            segments.remove(start);
+        }
+        *to_me = true;
+    }
+
+    /// 消息中回复的消息 ID
+    pub(crate) fn reply_id(&self) -> Option<i32> {
+        let MessageContent::Segment(segments) = self.try_message().ok()? else {
+            return None;
+        };
+        segments.iter().find_map(|segment| match segment {
+            MessageSegment::Reply { id } => id.parse().ok(),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn set_to_me(&mut self) {
+        if let Self::GroupMessage(GroupMessage { to_me, .. }) | Self::PrivateMessage(PrivateMessage { to_me, .. }) =
+            self
+        {
+            *to_me = true;
+        }
+    }
+}
+
+/// 去除 @ 之后留下的空白，文本为空时移除整个消息段
+fn trim_text(segments: &mut Vec<MessageSegment>, index: usize, trim: fn(&str) -> &str) {
+    if let Some(MessageSegment::Text { text }) = segments.get_mut(index) {
+        *text = trim(text).to_owned();
+        if text.is_empty() {
+            segments.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notice(fields: &str) -> Notice {
+        let json = format!(r#"{{"time":1700000000,"self_id":10000,"post_type":"notice",{fields}}}"#);
+        match serde_json::from_str(&json).unwrap() {
+            Event::Notice(notice) => notice,
+            event => panic!("{json} deserialized as {event:?}"),
+        }
+    }
+
+    #[test]
+    fn test_notice_deserialize() {
+        let cases = [
+            (
+                r#""notice_type":"group_upload","group_id":1,"user_id":2,"file":{"id":"abc","name":"a.txt","size":3,"busid":102}"#,
+                NoticeType::GroupUpload,
+            ),
+            (
+                r#""notice_type":"group_admin","sub_type":"set","group_id":1,"user_id":2"#,
+                NoticeType::GroupAdmin,
+            ),
+            (
+                r#""notice_type":"group_decrease","sub_type":"kick","group_id":1,"operator_id":3,"user_id":2"#,
+                NoticeType::GroupDecrease,
+            ),
+            (
+                r#""notice_type":"group_increase","sub_type":"approve","group_id":1,"operator_id":3,"user_id":2"#,
+                NoticeType::GroupIncrease,
+            ),
+            (
+                r#""notice_type":"group_ban","sub_type":"ban","group_id":1,"operator_id":3,"user_id":2,"duration":60"#,
+                NoticeType::GroupBan,
+            ),
+            (
+                r#""notice_type":"group_recall","group_id":1,"user_id":2,"operator_id":3,"message_id":4"#,
+                NoticeType::GroupRecall,
+            ),
+            (
+                r#""notice_type":"notify","sub_type":"poke","group_id":1,"user_id":2,"target_id":10000"#,
+                NoticeType::Poke,
+            ),
+            (
+                r#""notice_type":"notify","sub_type":"lucky_king","group_id":1,"user_id":2,"target_id":3"#,
+                NoticeType::LuckyKing,
+            ),
+            (
+                r#""notice_type":"notify","sub_type":"honor","group_id":1,"user_id":2,"honor_type":"talkative""#,
+                NoticeType::Honor,
+            ),
+            (
+                r#""notice_type":"group_card","group_id":1,"user_id":2,"card_new":"new","card_old":"old""#,
+                NoticeType::Other,
+            ),
+        ];
+        for (fields, notice_type) in cases {
+            let notice = notice(fields);
+            assert_eq!(notice.notice_type(), notice_type, "{fields}");
+            assert_eq!(notice.group_id(), Some(1), "{fields}");
+            assert_eq!(notice.user_id(), Some(2), "{fields}");
+        }
+        let essence = notice(
+            r#""notice_type":"essence","sub_type":"add","group_id":1,"sender_id":2,"operator_id":3,"message_id":4"#,
+        );
+        assert!(matches!(&essence.kind, NoticeKind::Other(other) if other.notice_type == "essence"));
+        assert_eq!((essence.group_id(), essence.user_id()), (Some(1), None));
+        // 没有 user_id 的通知不会被当作来自某个用户
+        assert!(Event::Notice(essence).try_user_id().is_err());
+    }
+
+    #[test]
+    fn test_private_notice_deserialize() {
+        let friend_add = notice(r#""notice_type":"friend_add","user_id":2"#);
+        assert_eq!(friend_add.notice_type(), NoticeType::FriendAdd);
+        assert_eq!((friend_add.group_id(), friend_add.user_id()), (None, Some(2)));
+        let friend_recall = notice(r#""notice_type":"friend_recall","user_id":2,"message_id":4"#);
+        assert_eq!(friend_recall.notice_type(), NoticeType::FriendRecall);
+        let poke = notice(r#""notice_type":"notify","sub_type":"poke","user_id":2,"target_id":10000"#);
+        assert_eq!((poke.notice_type(), poke.group_id()), (NoticeType::Poke, None));
+        let input_status = notice(
+            r#""notice_type":"notify","sub_type":"input_status","user_id":2,"status_text":"对方正在输入...","event_type":1"#,
+        );
+        assert_eq!(input_status.notice_type(), NoticeType::Other);
+        assert!(matches!(
+            &input_status.kind,
+            NoticeKind::Notify(Notify::Other(data)) if data["sub_type"] == "input_status"
+        ));
+        assert_eq!((input_status.group_id(), input_status.user_id()), (None, Some(2)));
+    }
+
+    fn group_message(segments: serde_json::Value) -> Event {
+        let mut event: Event = serde_json::from_value(serde_json::json!({
+            "time": 1700000000,
+            "self_id": 10000,
+            "post_type": "message",
+            "message_type": "group",
+            "sub_type": "normal",
+            "message_id": 1,
+            "group_id": 30000,
+            "user_id": 20000,
+            "message": segments,
+            "raw_message": "",
+            "font": 0,
+            "sender": { "user_id": 20000, "nickname": "kita" },
+        }))
+        .unwrap();
+        event.normalize();
+        event.strip_to_me(&["bocchi".to_string(), "波奇".to_string()]);
+        event
+    }
+
+    fn text(text: &str) -> serde_json::Value {
+        serde_json::json!({ "type": "text", "data": { "text": text } })
+    }
+
+    fn at(qq: &str) -> serde_json::Value {
+        serde_json::json!({ "type": "at", "data": { "qq": qq } })
+    }
+
+    #[test]
+    fn test_strip_to_me() {
+        let cases = [
+            (serde_json::json!([text("bocchi, hello")]), true, "hello"),
+            (serde_json::json!([text("  波奇：你好")]), true, "你好"),
+            (serde_json::json!([text("波奇你好")]), true, "你好"),
+            (serde_json::json!([text("bocchiXYZ hello")]), false, "bocchiXYZ hello"),
+            (serde_json::json!([text("bocchi2 hello")]), false, "bocchi2 hello"),
+            (serde_json::json!([text("hello bocchi")]), false, "hello bocchi"),
+            (serde_json::json!([at("10000"), text(" hello")]), true, "hello"),
+            (serde_json::json!([text("hello "), at("10000")]), true, "hello"),
+            (serde_json::json!([at("20000"), text(" hello")]), false, " hello"),
+            (
+                serde_json::json!([text("hello "), at("10000"), text(" world")]),
+                false,
+                "hello  world",
+            ),
+            (
+                serde_json::json!([{ "type": "reply", "data": { "id": "1" } }, at("10000"), text(" hello")]),
+                true,
+                "hello",
+            ),
+        ];
+        for (segments, to_me, plain_text) in cases {
+            let event = group_message(segments.clone());
+            assert_eq!(event.is_to_me(), to_me, "{segments}");
+            assert_eq!(event.plain_text(), plain_text, "{segments}");
+        }
+        // 只有昵称时整个文本消息段被移除
+        let event = group_message(serde_json::json!([text("bocchi")]));
+        assert!(event.is_to_me());
+        assert!(matches!(event.message(), MessageContent::Segment(segments) if segments.is_empty()));
+        // 只有 @bot 时不会被当作结尾的 @ 重复处理
+        let event = group_message(serde_json::json!([at("10000")]));
+        assert!(event.is_to_me());
+    }
+
+    #[test]
+    fn test_group_role() {
+        let sender = |role: Option<&str>| Sender {
+            user_id: Some(20000),
+            nickname: None,
+            card: None,
+            sex: None,
+            age: None,
+            area: None,
+            level: None,
+            role: role.map(str::to_owned),
+            title: None,
+        };
+        assert_eq!(sender(Some("owner")).group_role(), Some(Role::Owner));
+        assert_eq!(sender(Some("admin")).group_role(), Some(Role::Admin));
+        assert_eq!(sender(Some("member")).group_role(), Some(Role::Member));
+        assert_eq!(sender(Some("unknown")).group_role(), None);
+        assert_eq!(sender(None).group_role(), None);
+        assert!(Role::Member < Role::Admin && Role::Admin < Role::Owner);
+    }
+
+    #[test]
+    fn test_request_is_not_notice() {
+        let event: Event = serde_json::from_str(
+            r#"{"time":1700000000,"self_id":10000,"post_type":"request","request_type":"friend","user_id":2,"comment":"hi","flag":"f"}"#,
+        )
+        .unwrap();
+        assert!(matches!(event, Event::FriendRequest(_)));
+    }
 }
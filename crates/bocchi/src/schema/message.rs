@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::schema::cq_code;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case", tag = "type", content = "data")]
 pub enum MessageSegment {
@@ -204,3 +206,48 @@ pub enum MessageContent {
     Text(String),
     Segment(Vec<MessageSegment>),
 }
+
+impl From<Vec<MessageSegment>> for MessageContent {
+    fn from(segments: Vec<MessageSegment>) -> Self {
+        MessageContent::Segment(segments)
+    }
+}
+
+impl MessageContent {
+    /// 转换为消息段，字符串格式的消息会按照 CQ 码解析
+    pub fn into_segments(self) -> Vec<MessageSegment> {
+        match self {
+            Self::Text(text) => cq_code::parse(&text),
+            Self::Segment(segments) => segments,
+        }
+    }
+
+    /// 转换为包含 CQ 码的字符串格式
+    pub fn to_cq_code(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Segment(segments) => cq_code::serialize(segments),
+        }
+    }
+
+    /// 将字符串格式的消息原地解析为消息段
+    pub fn normalize(&mut self) {
+        if let Self::Text(text) = self {
+            *self = Self::Segment(cq_code::parse(text));
+        }
+    }
+
+    /// 消息中所有纯文本消息段拼接得到的文本
+    pub fn plain_text(&self) -> String {
+        match self {
+            Self::Text(text) => Self::Segment(cq_code::parse(text)).plain_text(),
+            Self::Segment(segments) => segments
+                .iter()
+                .filter_map(|seg| match seg {
+                    MessageSegment::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,9 +1,12 @@
 mod api;
+mod builder;
+pub mod cq_code;
 mod emoji;
 mod event;
 mod message;
 
 pub use api::*;
+pub use builder::MessageBuilder;
 pub use emoji::Emoji;
-pub use event::{Event, Sender};
+pub use event::*;
 pub use message::{MessageContent, MessageSegment};
@@ -0,0 +1,156 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    sync::RwLock,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::Event;
+
+/// 插件开关的作用范围，每个群组与私聊分别设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Group(u64),
+    Private(u64),
+}
+
+impl Scope {
+    /// 群消息使用群组，其余事件使用发送者所在的私聊，无法确定时返回 None
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event.try_group_id() {
+            Ok(group_id) => Some(Self::Group(group_id)),
+            Err(_) => event.try_user_id().ok().map(Self::Private),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Disabled {
+    groups: HashMap<u64, BTreeSet<String>>,
+    privates: HashMap<u64, BTreeSet<String>>,
+}
+
+impl Disabled {
+    fn get(&self, scope: Scope) -> Option<&BTreeSet<String>> {
+        match scope {
+            Scope::Group(id) => self.groups.get(&id),
+            Scope::Private(id) => self.privates.get(&id),
+        }
+    }
+
+    fn entry(&mut self, scope: Scope) -> &mut BTreeSet<String> {
+        match scope {
+            Scope::Group(id) => self.groups.entry(id).or_default(),
+            Scope::Private(id) => self.privates.entry(id).or_default(),
+        }
+    }
+}
+
+/// 记录各个群组与私聊中被禁用的插件，所有插件默认启用
+///
+/// 通过 `PluginSwitches::load` 创建时，每次修改都会写回文件，重启后保持不变
+#[derive(Debug, Default)]
+pub struct PluginSwitches {
+    path: Option<PathBuf>,
+    disabled: RwLock<Disabled>,
+    /// 保证写入文件的顺序与修改的顺序一致
+    save_lock: tokio::sync::Mutex<()>,
+}
+
+impl PluginSwitches {
+    /// 从 JSON 文件中读取开关状态，文件不存在时视为所有插件均启用
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let disabled = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Disabled::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            disabled: RwLock::new(disabled),
+            save_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn is_enabled(&self, scope: Scope, plugin: &str) -> bool {
+        self.disabled
+            .read()
+            .unwrap()
+            .get(scope)
+            .is_none_or(|disabled| !disabled.contains(plugin))
+    }
+
+    /// 该范围内被禁用的插件，按名称排序
+    pub fn disabled(&self, scope: Scope) -> Vec<String> {
+        self.disabled
+            .read()
+            .unwrap()
+            .get(scope)
+            .map(|disabled| disabled.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 修改开关状态，写入文件失败时返回 Err，此时内存中的状态保持不变
+    pub async fn set_enabled(&self, scope: Scope, plugin: &str, enabled: bool) -> Result<()> {
+        let _guard = self.save_lock.lock().await;
+        // 持有 save_lock 时没有其他修改，在副本上修改并写入文件成功后再替换
+        let mut updated = self.disabled.read().unwrap().clone();
+        let entry = updated.entry(scope);
+        if enabled {
+            entry.remove(plugin);
+        } else {
+            entry.insert(plugin.to_owned());
+        }
+        if let Some(path) = &self.path {
+            let content = serde_json::to_vec_pretty(&updated)?;
+            // 先写入临时文件再重命名，避免写入中途退出时留下不完整的文件
+            let mut temp = path.clone().into_os_string();
+            temp.push(".tmp");
+            tokio::fs::write(&temp, content).await?;
+            tokio::fs::rename(&temp, path).await?;
+        }
+        *self.disabled.write().unwrap() = updated;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_and_save() {
+        let path = std::env::temp_dir().join(format!("bocchi-switches-{}.json", crate::media::token()));
+        let switches = PluginSwitches::load(&path).unwrap();
+        assert!(switches.is_enabled(Scope::Group(1), "echo"));
+        switches.set_enabled(Scope::Group(1), "echo", false).await.unwrap();
+        switches.set_enabled(Scope::Private(2), "ip", false).await.unwrap();
+        switches.set_enabled(Scope::Private(2), "gpt", false).await.unwrap();
+        switches.set_enabled(Scope::Private(2), "gpt", true).await.unwrap();
+        // 开关只在对应的范围内生效
+        assert!(!switches.is_enabled(Scope::Group(1), "echo"));
+        assert!(switches.is_enabled(Scope::Group(2), "echo"));
+        assert!(switches.is_enabled(Scope::Private(1), "echo"));
+        let loaded = PluginSwitches::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!loaded.is_enabled(Scope::Group(1), "echo"));
+        assert!(!loaded.is_enabled(Scope::Private(2), "ip"));
+        assert!(loaded.is_enabled(Scope::Private(2), "gpt"));
+        assert_eq!(loaded.disabled(Scope::Private(2)), ["ip"]);
+    }
+
+    #[tokio::test]
+    async fn test_save_failure() {
+        // 目录不存在，写入文件会失败
+        let path = std::env::temp_dir()
+            .join(format!("bocchi-missing-{}", crate::media::token()))
+            .join("switches.json");
+        let switches = PluginSwitches::load(&path).unwrap();
+        assert!(switches.set_enabled(Scope::Group(1), "echo", false).await.is_err());
+        assert!(switches.is_enabled(Scope::Group(1), "echo"));
+        assert!(switches.disabled(Scope::Group(1)).is_empty());
+    }
+}
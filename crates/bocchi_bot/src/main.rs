@@ -26,6 +26,8 @@ fn init() {
 async fn main() -> Result<()> {
     init();
     let mut bot = Bot::connect("ws://localhost:3001").await?;
+    bot.set_nicknames(["波奇酱", "bocchi"]);
+    bot.load_plugin_switches("./plugin_switches.json")?;
     bot.use_builtin_handler();
     for plugin in [
         plugin::bonus_plugin(),
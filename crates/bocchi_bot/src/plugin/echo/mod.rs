@@ -1,22 +1,18 @@
-use bocchi::{chain::Rule, plugin::Plugin};
+use bocchi::{
+    chain::{Context, RawArgs, Rule},
+    plugin::Plugin,
+};
 
 pub fn echo_plugin() -> Plugin {
     let mut plugin = Plugin::new("回显插件", "回显用户输入的文本");
 
-    plugin.on(
+    plugin.on_extract(
         "原样输出 echo 后的内容",
         i32::default(),
-        Rule::on_message() & Rule::on_prefix("#echo"),
-        |ctx| async move {
-            let plain_text = ctx
-                .event
-                .plain_text()
-                .trim()
-                .trim_start_matches("#echo")
-                .trim()
-                .to_owned();
-            if !plain_text.is_empty() {
-                ctx.send(plain_text).await?;
+        Rule::on_message() & Rule::on_command("echo", &[]),
+        |ctx: Context, RawArgs(text): RawArgs| async move {
+            if !text.is_empty() {
+                ctx.send(text).await?;
             }
             Ok(true)
         },
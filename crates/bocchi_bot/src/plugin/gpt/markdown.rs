@@ -3,7 +3,7 @@
 use std::sync::LazyLock;
 
 use aho_corasick::AhoCorasick;
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use async_tempfile::TempFile;
 use fantoccini::Locator;
 use tokio::{
@@ -20,7 +20,7 @@ static GECKO_DRIVER_INITED: OnceCell<()> = OnceCell::const_new();
 static FANTOCCINI_CLIENT: OnceCell<Mutex<fantoccini::Client>> = OnceCell::const_new();
 static AHO_CORASICK: LazyLock<AhoCorasick> = LazyLock::new(|| AhoCorasick::new([r"\[", r"\]", r"\(", r"\)"]).unwrap());
 
-async fn run_gecko_driver() {
+async fn run_gecko_driver() -> Result<()> {
     let mut gecko_driver_process = Command::new(GECKO_DRIVER_BINARY)
         .args(["--port", PORT, "--binary", FIREFOX_BINARY])
         .stdin(std::process::Stdio::null())
@@ -28,24 +28,31 @@ async fn run_gecko_driver() {
         .stderr(std::process::Stdio::null())
         .kill_on_drop(true)
         .spawn()
-        .expect("启动 Gecko 驱动失败");
-    match gecko_driver_process.stdout.take() {
-        None => panic!("获取 Gecko 输出失败"),
-        Some(stdout) => {
-            let mut reader = BufReader::new(stdout);
-            let mut buf = String::new();
-            reader.read_line(&mut buf).await.expect("读取 Gecko 输出失败");
-            // 目前很简单，当输出中包含监听地址时，认为启动成功
-            if !buf.contains(&format!("Listening on 127.0.0.1:{PORT}")) {
-                panic!("启动 Gecko 驱动失败");
-            }
-            // 重要：必须将 stdout 重新放回 gecko_driver_process，否则后续日志找不到 pipe 输出，process 会中断
-            gecko_driver_process.stdout = Some(reader.into_inner());
-            // 阻塞等待 gecko_driver 退出
-            tokio::spawn(async move {
-                gecko_driver_process.wait().await.expect("Gecko 驱动异常退出");
-            });
+        .context("启动 Gecko 驱动失败")?;
+    let stdout = gecko_driver_process.stdout.take().context("获取 Gecko 输出失败")?;
+    let mut reader = BufReader::new(stdout);
+    let mut buf = String::new();
+    reader.read_line(&mut buf).await.context("读取 Gecko 输出失败")?;
+    // 目前很简单，当输出中包含监听地址时，认为启动成功
+    if !buf.contains(&format!("Listening on 127.0.0.1:{PORT}")) {
+        bail!("启动 Gecko 驱动失败: {}", buf.trim());
+    }
+    // 重要：必须将 stdout 重新放回 gecko_driver_process，否则后续日志找不到 pipe 输出，process 会中断
+    gecko_driver_process.stdout = Some(reader.into_inner());
+    // 阻塞等待 gecko_driver 退出
+    tokio::spawn(async move {
+        match gecko_driver_process.wait().await {
+            Ok(status) => error!("Gecko 驱动退出: {status}"),
+            Err(e) => error!("等待 Gecko 驱动失败: {e:?}"),
         }
+    });
+    Ok(())
+}
+
+/// 提前启动 gecko 驱动，避免第一次渲染时等待，启动失败时会在下次渲染时重试
+pub async fn warm_up() {
+    if let Err(e) = GECKO_DRIVER_INITED.get_or_try_init(run_gecko_driver).await {
+        error!("预热 Gecko 驱动失败: {e:?}");
     }
 }
 
@@ -67,12 +74,11 @@ async fn markdown_to_html(markdown: String) -> Result<String> {
 }
 
 async fn html_to_image(html: &str) -> Result<Vec<u8>> {
-    GECKO_DRIVER_INITED.get_or_init(run_gecko_driver).await;
+    GECKO_DRIVER_INITED.get_or_try_init(run_gecko_driver).await?;
     // client 构建必须要晚于 gecko 驱动的启动
     let browser = FANTOCCINI_CLIENT
-        .get_or_init(|| async {
-            let client = fantoccini::ClientBuilder::rustls()
-                .unwrap()
+        .get_or_try_init(|| async {
+            let client = fantoccini::ClientBuilder::rustls()?
                 .capabilities(serde_json::Map::from_iter(vec![(
                     "moz:firefoxOptions".to_string(),
                     serde_json::json!(
@@ -86,10 +92,10 @@ async fn html_to_image(html: &str) -> Result<Vec<u8>> {
                 )]))
                 .connect(&format!("http://localhost:{PORT}"))
                 .await
-                .expect("连接到 Gecko 驱动失败");
-            Mutex::new(client)
+                .context("连接到 Gecko 驱动失败")?;
+            anyhow::Ok(Mutex::new(client))
         })
-        .await;
+        .await?;
     let mut tempfile = TempFile::new().await?;
     tempfile.write_all(render(html).as_bytes()).await?;
     tempfile.flush().await?;
@@ -1,15 +1,14 @@
-use std::{env, sync::LazyLock};
+use std::{env, sync::LazyLock, time::Duration};
 
 use anyhow::{Error, Result};
-use async_tempfile::TempFile;
 use bocchi::{
-    chain::{Context, Rule},
+    chain::{Context, LimitScope, Rule},
     plugin::Plugin,
-    schema::{Emoji, MessageContent, MessageSegment},
+    schema::{Emoji, MessageBuilder, MessageSegment},
 };
 use dashmap::DashMap;
 use serde_json::{Value, json};
-use tokio::{io::AsyncWriteExt, sync::Mutex};
+use tokio::sync::Mutex;
 
 use crate::{
     migrate::database,
@@ -25,6 +24,12 @@ static LOCKS: LazyLock<DashMap<String, Mutex<()>>> = LazyLock::new(DashMap::new)
 pub fn gpt_plugin() -> Plugin {
     let mut plugin = Plugin::new("GPT 插件", "使用 DeepSeek API 进行对话");
 
+    // 在后台启动 gecko 驱动，启动失败时不影响 bot 的其他功能，第一次渲染时会再次尝试
+    plugin.on_startup(|_| async {
+        tokio::spawn(markdown::warm_up());
+        Ok(())
+    });
+
     for (description, command, max_tokens, reply_image) in [
         ("提问并获得文本答复", "#gpt", Some(512), false), // gpt 使用文本输出，需要文本内容较短
         ("提问并获得图片答复", "#igpt", None, true),      // igpt 使用图片输出，不需要限制 token
@@ -32,11 +37,22 @@ pub fn gpt_plugin() -> Plugin {
         plugin.on(
             description,
             i32::default(),
-            Rule::on_group_message() & Rule::on_prefix(command),
+            Rule::on_group_message()
+                & Rule::on_prefix(command)
+                & Rule::cooldown(LimitScope::User, Duration::from_secs(10)).reply_on_limit(),
             move |ctx| async move { call_deepseek_api(ctx, command, max_tokens, reply_image).await },
         )
     }
 
+    plugin.on(
+        "@ 机器人提问并获得文本答复",
+        i32::default() - 1, // 优先级低于其他插件，避免 @ 机器人使用其他命令时被当作提问
+        Rule::on_group_message()
+            & Rule::to_me()
+            & Rule::cooldown(LimitScope::User, Duration::from_secs(10)).reply_on_limit(),
+        |ctx| async move { call_deepseek_api(ctx, "#gpt", Some(512), false).await },
+    );
+
     for (description, command, lookup_command, reply_image) in [
         ("查询 gpt 历史记录", "#gpt_history", "#gpt", false),
         ("查询 igpt 历史记录", "#igpt_history", "#igpt", true),
@@ -142,27 +158,14 @@ async fn call_deepseek_api(
     rw.upsert(memory)?;
     rw.commit()?;
     drop(_guard);
-    let mut tempfile = TempFile::new().await?;
     let message = if reply_image {
-        tempfile.write_all(&markdown::markdown_to_image(text).await?).await?;
-        tempfile.flush().await?;
-        MessageSegment::Image {
-            /*
-            当前使用 /tmp 中转来进行 bot 与 onebot server 的文件传输，这要求 bot 与 onebot server 在同一台机器上，
-            且如果有容器等隔离环境，需要保证 /tmp 是共享的。更通用的做法是引入 base64 crates 通过 base64 传递图片。
-            */
-            file: format!("file://{}", tempfile.file_path().to_string_lossy()),
-            r#type: None,
-            url: None,
-            cache: Some(true),
-            proxy: None,
-            timeout: None,
-        }
+        // 图片的传输方式由 bot 的 MediaTransport 决定，这里无需关心临时文件
+        MessageBuilder::new().image_bytes(markdown::markdown_to_image(text).await?)
     } else {
-        MessageSegment::Text { text }
+        MessageBuilder::new().text(text)
     };
     ctx.set_reaction(emoji).await?;
-    ctx.reply_content(vec![message]).await?;
+    ctx.reply_content(message).await?;
     res
 }
 
@@ -178,50 +181,26 @@ async fn query_gpt_history(ctx: Context, command: &'static str, reply_image: boo
             ctx.reply(format!("没有找到 {} 历史记录", command_name)).await?;
             return Ok(true);
         }
-        let mut messages = Vec::new();
-        // 一般来说历史记录是一条用户一条 GPT，因此 len / 2 + 1 足够了
-        let mut tempfiles = Vec::with_capacity(memory.history.len() / 2 + 1);
+        let mut messages = MessageBuilder::new();
         for message in memory.history.iter() {
             match &message.sender {
                 Some(sender) => {
-                    messages.push(MessageSegment::Node {
+                    messages = messages.segment(MessageSegment::Node {
                         id: None,
                         user_id: sender.user_id.map(|id| id.to_string()),
                         nickname: sender.nickname.clone(),
-                        content: Some(MessageContent::Text(message.content.clone())),
+                        content: Some(MessageBuilder::new().text(message.content.clone()).into()),
                     });
                 }
                 None => {
-                    let message_user_id = Some(ctx.event.user_id().to_string());
-                    if reply_image {
-                        let mut tempfile = TempFile::new().await?;
-                        tempfile
-                            .write_all(&markdown::markdown_to_image(message.content.clone()).await?)
-                            .await?;
-                        tempfile.flush().await?;
-                        messages.push(MessageSegment::Node {
-                            id: None,
-                            user_id: message_user_id,
-                            nickname: Some(command_name.clone()),
-                            content: Some(MessageContent::Segment(vec![MessageSegment::Image {
-                                file: format!("file://{}", tempfile.file_path().to_string_lossy()),
-                                r#type: None,
-                                url: None,
-                                cache: Some(true),
-                                proxy: Some(false),
-                                timeout: None,
-                            }])),
-                        });
-                        // 这里很关键，如果不将 tempfile 所有权转移出去，超出 scope 时会被 drop，临时文件被删除
-                        tempfiles.push(tempfile);
+                    let content = if reply_image {
+                        MessageBuilder::new()
+                            .image_bytes(markdown::markdown_to_image(message.content.clone()).await?)
+                            .proxy(false)
                     } else {
-                        messages.push(MessageSegment::Node {
-                            id: None,
-                            user_id: message_user_id,
-                            nickname: Some(command_name.clone()),
-                            content: Some(MessageContent::Text(message.content.clone())),
-                        });
-                    }
+                        MessageBuilder::new().text(message.content.clone())
+                    };
+                    messages = messages.node(ctx.event.user_id(), command_name.clone(), content);
                 }
             }
         }
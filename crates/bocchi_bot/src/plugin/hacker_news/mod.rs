@@ -1,6 +1,11 @@
 use std::fmt::Display;
 
-use bocchi::{chain::Rule, plugin::Plugin};
+use anyhow::Result;
+use bocchi::{
+    chain::Rule,
+    plugin::Plugin,
+    schema::{MessageBuilder, MessageContent, SendForwardMsgParams},
+};
 use futures::{StreamExt, stream::FuturesOrdered};
 use serde::Deserialize;
 
@@ -23,6 +28,9 @@ impl Display for HackerStory {
     }
 }
 
+/// 每日摘要发送到的群组
+const DIGEST_GROUP_ID: u64 = 954985908;
+
 pub fn hacker_news_plugin() -> Plugin {
     let mut plugin = Plugin::new("Hacker News 插件", "获取 Hacker News 的内容");
 
@@ -31,36 +39,60 @@ pub fn hacker_news_plugin() -> Plugin {
         i32::default(),
         Rule::on_message() & Rule::on_exact_match("#hn"),
         |ctx| async move {
-            let ids = HTTP_CLIENT
-                .get("https://hacker-news.firebaseio.com/v0/topstories.json")
-                .send()
-                .await?
-                .error_for_status()?
-                .json::<Vec<i64>>()
-                .await?;
-            let future_ordered = ids
-                .into_iter()
-                .take(10)
-                .map(|id| async move {
-                    HTTP_CLIENT
-                        .get(format!("https://hacker-news.firebaseio.com/v0/item/{}.json", id))
-                        .send()
-                        .await?
-                        .error_for_status()?
-                        .json::<HackerStory>()
-                        .await
-                })
-                .collect::<FuturesOrdered<_>>();
-            let res = future_ordered
-                .collect::<Vec<_>>()
-                .await
-                .into_iter()
-                .filter_map(|story| story.ok().map(|story| story.to_string()))
-                .collect::<Vec<_>>();
-            ctx.send_forward(res).await?;
+            ctx.send_forward(top_stories().await?).await?;
             Ok(true)
         },
     );
 
     plugin
+        .schedule("每天早上发送 Hacker News top 10", "0 0 9 * * *", |caller| async move {
+            let login_info = caller.get_login_info().await?;
+            let messages = top_stories()
+                .await?
+                .into_iter()
+                .fold(MessageBuilder::new(), |builder, story| {
+                    builder.node(login_info.user_id, &login_info.nickname, story)
+                });
+            caller
+                .send_forward_msg(SendForwardMsgParams {
+                    user_id: None,
+                    group_id: Some(DIGEST_GROUP_ID),
+                    messages: MessageContent::Segment(messages.build()),
+                    message_type: None,
+                })
+                .await?;
+            Ok(())
+        })
+        .expect("cron 表达式为常量，解析不会失败");
+
+    plugin
+}
+
+async fn top_stories() -> Result<Vec<String>> {
+    let ids = HTTP_CLIENT
+        .get("https://hacker-news.firebaseio.com/v0/topstories.json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<i64>>()
+        .await?;
+    let future_ordered = ids
+        .into_iter()
+        .take(10)
+        .map(|id| async move {
+            HTTP_CLIENT
+                .get(format!("https://hacker-news.firebaseio.com/v0/item/{}.json", id))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<HackerStory>()
+                .await
+        })
+        .collect::<FuturesOrdered<_>>();
+    Ok(future_ordered
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .filter_map(|story| story.ok().map(|story| story.to_string()))
+        .collect())
 }
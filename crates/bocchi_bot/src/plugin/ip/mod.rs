@@ -1,7 +1,7 @@
 use std::{net::IpAddr, time::Duration};
 
 use anyhow::{Context, Result};
-use bocchi::{chain::Rule, plugin::Plugin};
+use bocchi::{chain::Rule, permission::Acl, plugin::Plugin};
 
 use crate::utils::HTTP_CLIENT;
 
@@ -9,11 +9,15 @@ const IP_API_URL: &str = "https://myip.ipip.net/s";
 
 pub fn ip_plugin() -> Plugin {
     let mut plugin = Plugin::new("IP 插件", "获取服务器公网 IP");
+    plugin.set_acl(Acl {
+        allow_groups: [954985908].into(),
+        ..Default::default()
+    });
 
     plugin.on(
         "获取服务器公网 IP",
         i32::default(),
-        Rule::on_group_id(954985908) & Rule::on_exact_match("#ip"),
+        Rule::on_exact_match("#ip"),
         |ctx| async move {
             let response = match public_ip().await {
                 Ok(ip) => ip.to_string(),
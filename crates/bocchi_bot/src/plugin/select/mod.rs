@@ -7,12 +7,10 @@ pub fn select_plugin() -> Plugin {
     plugin.on(
         "随机选择",
         i32::default(),
-        Rule::on_message() & Rule::on_prefix("#select"),
+        Rule::on_message() & Rule::on_command("select", &[]),
         |ctx| async move {
-            let plain_text = ctx.event.plain_text();
-            let choices = plain_text
-                .trim()
-                .trim_start_matches("#select")
+            let choices = ctx
+                .raw_args()
                 .split("/")
                 .map(|s| s.trim())
                 .filter(|s| !s.is_empty())
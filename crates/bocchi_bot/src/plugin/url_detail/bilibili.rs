@@ -3,10 +3,7 @@ use std::{sync::LazyLock, time::Duration};
 use bocchi::schema::MessageSegment;
 use serde::Deserialize;
 
-use crate::{
-    plugin::url_detail::{RecognizedContent, RecognizedMessage},
-    utils::HTTP_CLIENT,
-};
+use crate::{plugin::url_detail::RecognizedContent, utils::HTTP_CLIENT};
 
 static BILIBILI_AV_REGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"https?://(?:www\.)?bilibili\.com/video/av(\d+)").unwrap());
@@ -75,7 +72,7 @@ async fn parse_video_id(text: &str) -> Option<VideoID> {
     parse_raw_video_id(url_after_redirect)
 }
 
-pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
+pub(crate) async fn recognizer(text: &str) -> Option<RecognizedContent> {
     let video_id = parse_video_id(text).await?;
     let url = match video_id {
         VideoID::AV(id) => format!("https://api.bilibili.com/x/web-interface/view?aid={}", id),
@@ -92,8 +89,8 @@ pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
         .ok()?;
     let data = resp.get("data")?.clone();
     let video_detail: VideoDetail = serde_json::from_value(data).ok()?;
-    Some(RecognizedMessage::new(
-        RecognizedContent::Normal(vec![
+    Some(RecognizedContent::Normal(
+        vec![
             MessageSegment::Image {
                 file: video_detail.pic,
                 r#type: None,
@@ -110,8 +107,8 @@ pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
                     video_detail.pubdate.with_timezone(&chrono::Local)
                 ),
             },
-        ]),
-        Vec::new(),
+        ]
+        .into(),
     ))
 }
 
@@ -7,28 +7,12 @@ mod youtube;
 
 use std::{future::Future, pin::Pin};
 
-use async_tempfile::TempFile;
-use bocchi::{
-    chain::Rule,
-    plugin::Plugin,
-    schema::{MessageContent, MessageSegment},
-};
+use bocchi::{chain::Rule, plugin::Plugin, schema::MessageBuilder};
 use futures::{StreamExt, stream::FuturesUnordered};
 
 pub(crate) enum RecognizedContent {
-    Normal(Vec<MessageSegment>),
-    Forward(Vec<MessageContent>),
-}
-
-pub(crate) struct RecognizedMessage {
-    content: RecognizedContent,
-    temp_files: Vec<TempFile>,
-}
-
-impl RecognizedMessage {
-    pub(crate) fn new(content: RecognizedContent, temp_files: Vec<TempFile>) -> Self {
-        Self { content, temp_files }
-    }
+    Normal(MessageBuilder),
+    Forward(Vec<MessageBuilder>),
 }
 
 pub fn url_detail_plugin() -> Plugin {
@@ -39,7 +23,7 @@ pub fn url_detail_plugin() -> Plugin {
         Rule::on_group_message(),
         |ctx| async move {
             let plain_text = ctx.event.plain_text();
-            let futures: [Pin<Box<dyn Future<Output = Option<RecognizedMessage>> + Send>>; 6] = [
+            let futures: [Pin<Box<dyn Future<Output = Option<RecognizedContent>> + Send>>; 6] = [
                 Box::pin(bilibili::recognizer(&plain_text)),
                 Box::pin(youtube::recognizer(&plain_text)),
                 Box::pin(spotify::recognizer(&plain_text)),
@@ -49,15 +33,13 @@ pub fn url_detail_plugin() -> Plugin {
             ];
             let mut futures_unordered = futures.into_iter().collect::<FuturesUnordered<_>>();
             while let Some(res) = futures_unordered.next().await {
-                let Some(message) = res else {
+                let Some(content) = res else {
                     continue;
                 };
-                let RecognizedMessage { content, temp_files } = message;
                 let send_result = match content {
                     RecognizedContent::Normal(message) => ctx.reply_content(message).await,
                     RecognizedContent::Forward(messages) => ctx.send_forward_content(messages).await,
                 };
-                drop(temp_files);
                 if let Err(e) = send_result {
                     error!("获取消息成功但发送失败: {:?}", e);
                 }
@@ -1,14 +1,12 @@
 use std::{sync::LazyLock, time::Duration};
 
 use anyhow::{Context, Result, bail};
-use async_tempfile::TempFile;
-use bocchi::schema::{MessageContent, MessageSegment};
+use bocchi::{media::Media, schema::MessageBuilder};
 use futures::{StreamExt, stream::FuturesOrdered};
 use reqwest::header;
 use serde::Deserialize;
-use tokio::io::AsyncWriteExt;
 
-use super::{RecognizedContent, RecognizedMessage};
+use super::RecognizedContent;
 use crate::utils::HTTP_CLIENT;
 
 const PIXIV_ORIGIN: &str = "https://www.pixiv.net";
@@ -69,22 +67,13 @@ struct PageUrls {
     original: String,
 }
 
-struct ParsedArtwork {
-    segments: Vec<MessageSegment>,
-    temp_files: Vec<TempFile>,
-}
-
-pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
+pub(crate) async fn recognizer(text: &str) -> Option<RecognizedContent> {
     let links = parse_links(text);
     match links.len() {
         0 => None,
         1 => {
             let link = links.into_iter().next()?;
-            let artwork = recognize_or_error(&link).await;
-            Some(RecognizedMessage::new(
-                RecognizedContent::Normal(artwork.segments),
-                artwork.temp_files,
-            ))
+            Some(RecognizedContent::Normal(recognize_or_error(&link).await))
         }
         _ => Some(recognize_many(links).await),
     }
@@ -102,7 +91,7 @@ fn parse_links(text: &str) -> Vec<PixivLink> {
         .collect()
 }
 
-async fn recognize_many(links: Vec<PixivLink>) -> RecognizedMessage {
+async fn recognize_many(links: Vec<PixivLink>) -> RecognizedContent {
     let mut futures = links
         .into_iter()
         .map(|link| async move {
@@ -112,33 +101,23 @@ async fn recognize_many(links: Vec<PixivLink>) -> RecognizedMessage {
         .collect::<FuturesOrdered<_>>();
 
     let mut messages = Vec::with_capacity(futures.len());
-    let mut temp_files = Vec::new();
-    while let Some((url, mut artwork)) = futures.next().await {
-        artwork.segments.push(MessageSegment::Text {
-            text: format!("\n链接：{url}"),
-        });
-        messages.push(MessageContent::Segment(artwork.segments));
-        temp_files.append(&mut artwork.temp_files);
+    while let Some((url, artwork)) = futures.next().await {
+        messages.push(artwork.text(format!("\n链接：{url}")));
     }
-    RecognizedMessage::new(RecognizedContent::Forward(messages), temp_files)
+    RecognizedContent::Forward(messages)
 }
 
-async fn recognize_or_error(link: &PixivLink) -> ParsedArtwork {
+async fn recognize_or_error(link: &PixivLink) -> MessageBuilder {
     match recognize_one(link).await {
         Ok(artwork) => artwork,
         Err(error) => {
             warn!("Pixiv 链接解析失败: {}, {error:#}", link.url);
-            ParsedArtwork {
-                segments: vec![MessageSegment::Text {
-                    text: format!("Pixiv 链接解析失败：{error:#}"),
-                }],
-                temp_files: Vec::new(),
-            }
+            MessageBuilder::new().text(format!("Pixiv 链接解析失败：{error:#}"))
         }
     }
 }
 
-async fn recognize_one(link: &PixivLink) -> Result<ParsedArtwork> {
+async fn recognize_one(link: &PixivLink) -> Result<MessageBuilder> {
     let detail: IllustDetail = fetch_ajax(&format!("{PIXIV_ORIGIN}/ajax/illust/{}?lang=zh", link.illust_id))
         .await
         .context("获取作品信息失败")?;
@@ -166,12 +145,10 @@ async fn recognize_one(link: &PixivLink) -> Result<ParsedArtwork> {
         .map(|(index, page)| async move { download_image(index, &page.urls.original).await })
         .collect::<FuturesOrdered<_>>();
 
-    let mut segments = Vec::with_capacity(shown_count + 2);
-    let mut temp_files = Vec::with_capacity(shown_count);
+    let mut message = MessageBuilder::new();
     while let Some(result) = downloads.next().await {
-        let (segment, temp_file) = result.context("下载插画失败")?;
-        segments.push(segment);
-        temp_files.push(temp_file);
+        let image = result.context("下载插画失败")?;
+        message = message.image_media(image).cache(false).timeout(15);
     }
 
     let tags = detail
@@ -202,9 +179,7 @@ async fn recognize_one(link: &PixivLink) -> Result<ParsedArtwork> {
             total_page_count, MAX_IMAGES, link.url
         ));
     }
-    segments.push(MessageSegment::Text { text: detail_text });
-
-    Ok(ParsedArtwork { segments, temp_files })
+    Ok(message.text(detail_text))
 }
 
 async fn fetch_ajax<T: for<'de> Deserialize<'de>>(url: &str) -> Result<T> {
@@ -224,7 +199,7 @@ async fn fetch_ajax<T: for<'de> Deserialize<'de>>(url: &str) -> Result<T> {
     serde_json::from_value(response.body).context("Pixiv 返回的数据格式不正确")
 }
 
-async fn download_image(index: usize, url: &str) -> Result<(MessageSegment, TempFile)> {
+async fn download_image(index: usize, url: &str) -> Result<Media> {
     let response = HTTP_CLIENT
         .get(url)
         .header(header::USER_AGENT, USER_AGENT)
@@ -242,19 +217,8 @@ async fn download_image(index: usize, url: &str) -> Result<(MessageSegment, Temp
         bail!("第 {} 张图片返回了非图片内容", index + 1);
     }
     let bytes = response.bytes().await?;
-    let mut temp_file = TempFile::new().await?;
-    temp_file.write_all(&bytes).await?;
-    temp_file.flush().await?;
-    let segment = MessageSegment::Image {
-        file: format!("file://{}", temp_file.file_path().to_string_lossy()),
-        r#type: None,
-        url: None,
-        cache: Some(false),
-        proxy: None,
-        timeout: Some(15),
-    };
-    debug!("Pixiv 第 {} 张图片已写入临时文件", index + 1);
-    Ok((segment, temp_file))
+    debug!("Pixiv 第 {} 张图片下载完成", index + 1);
+    Ok(bytes.into())
 }
 
 #[cfg(test)]
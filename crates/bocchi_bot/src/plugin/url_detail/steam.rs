@@ -5,7 +5,7 @@ use bocchi::schema::MessageSegment;
 use scraper::Html;
 use serde::Deserialize;
 
-use super::{RecognizedContent, RecognizedMessage};
+use super::RecognizedContent;
 use crate::utils::HTTP_CLIENT;
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
@@ -78,12 +78,9 @@ struct ReviewSummary {
     total_reviews: u64,
 }
 
-pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
+pub(crate) async fn recognizer(text: &str) -> Option<RecognizedContent> {
     let link = parse_link(text)?;
-    Some(RecognizedMessage::new(
-        RecognizedContent::Normal(recognize_or_error(&link).await),
-        Vec::new(),
-    ))
+    Some(RecognizedContent::Normal(recognize_or_error(&link).await.into()))
 }
 
 fn parse_link(text: &str) -> Option<SteamLink> {
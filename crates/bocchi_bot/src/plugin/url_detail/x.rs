@@ -1,12 +1,12 @@
 use std::{num::NonZeroU32, sync::LazyLock, time::Duration};
 
-use bocchi::schema::{MessageContent, MessageSegment};
+use bocchi::schema::MessageBuilder;
 use futures::{StreamExt, stream::FuturesOrdered};
 use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use reqwest::{StatusCode, header};
 use scraper::{Html, Selector};
 
-use super::{RecognizedContent, RecognizedMessage};
+use super::RecognizedContent;
 use crate::utils::HTTP_CLIENT;
 
 static X_REGEX: LazyLock<regex::Regex> =
@@ -119,7 +119,7 @@ async fn fetch_nitter_html(url: &str) -> Option<String> {
     None
 }
 
-pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
+pub(crate) async fn recognizer(text: &str) -> Option<RecognizedContent> {
     let links: Vec<XLink> = X_REGEX
         .captures_iter(text)
         .filter_map(|caps| {
@@ -135,17 +135,13 @@ pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
         0 => None,
         1 => {
             let link = links.into_iter().next()?;
-            recognize_one(&link)
-                .await
-                .map(|content| RecognizedMessage::new(RecognizedContent::Normal(content), Vec::new()))
+            recognize_one(&link).await.map(RecognizedContent::Normal)
         }
-        _ => recognize_many(links)
-            .await
-            .map(|content| RecognizedMessage::new(RecognizedContent::Forward(content), Vec::new())),
+        _ => recognize_many(links).await.map(RecognizedContent::Forward),
     }
 }
 
-async fn recognize_many(links: Vec<XLink>) -> Option<Vec<MessageContent>> {
+async fn recognize_many(links: Vec<XLink>) -> Option<Vec<MessageBuilder>> {
     let mut futures = links
         .into_iter()
         .map(|link| async move {
@@ -157,19 +153,14 @@ async fn recognize_many(links: Vec<XLink>) -> Option<Vec<MessageContent>> {
     let mut messages = Vec::with_capacity(futures.len());
     while let Some((url, result)) = futures.next().await {
         let content = result
-            .map(|mut segments| {
-                segments.push(MessageSegment::Text {
-                    text: format!("\n链接：{}", url),
-                });
-                MessageContent::Segment(segments)
-            })
-            .unwrap_or_else(|| MessageContent::Text(format!("X 链接解析失败：\n{}", url)));
+            .map(|message| message.text(format!("\n链接：{}", url)))
+            .unwrap_or_else(|| MessageBuilder::new().text(format!("X 链接解析失败：\n{}", url)));
         messages.push(content);
     }
     Some(messages)
 }
 
-async fn recognize_one(link: &XLink) -> Option<Vec<MessageSegment>> {
+async fn recognize_one(link: &XLink) -> Option<MessageBuilder> {
     let username = link.username.as_str();
     let status_id = link.status_id.as_str();
     let nitter_url = format!("{}/{}/status/{}", NITTER_ORIGIN, username, status_id);
@@ -215,7 +206,7 @@ async fn recognize_one(link: &XLink) -> Option<Vec<MessageSegment>> {
     let likes = stats.get(2).cloned().unwrap_or_else(|| "0".to_string());
     let views = stats.get(3).cloned().unwrap_or_else(|| "0".to_string());
 
-    let mut segments = Vec::new();
+    let mut message = MessageBuilder::new();
     let mut last_was_image = false;
 
     let mut text_top = format!("@{}", author_username);
@@ -226,25 +217,16 @@ async fn recognize_one(link: &XLink) -> Option<Vec<MessageSegment>> {
         text_top.push_str(&format!(" ({})", fullname));
     }
     text_top.push_str(&format!(":\n{}", content));
-    segments.push(MessageSegment::Text { text: text_top });
+    message = message.text(text_top);
 
     for img_url in extract_images(&tweet_body, &MAIN_ATTACHMENT_IMG_SEL) {
-        segments.push(MessageSegment::Image {
-            file: img_url,
-            r#type: None,
-            url: None,
-            cache: Some(true),
-            proxy: Some(false),
-            timeout: Some(10),
-        });
+        message = message.image_url(img_url).proxy(false).timeout(10);
         last_was_image = true;
     }
 
     if tweet_body.select(&MAIN_GALLERY_VIDEO_SEL).next().is_some() {
         let br = if last_was_image { "" } else { "\n" };
-        segments.push(MessageSegment::Text {
-            text: format!("{}[推文中含有视频，当前不支持解析]", br),
-        });
+        message = message.text(format!("{}[推文中含有视频，当前不支持解析]", br));
         last_was_image = false;
     }
 
@@ -254,9 +236,7 @@ async fn recognize_one(link: &XLink) -> Option<Vec<MessageSegment>> {
         line.push_str(&format!("🕒 {}", date));
     }
     line.push_str(&format!(" | 💬 {} 🔄 {} ❤️ {} 👁 {}", comments, retweets, likes, views));
-    segments.push(MessageSegment::Text {
-        text: format!("{}{}", br, line),
-    });
+    message = message.text(format!("{}{}", br, line));
 
     if let Some(quote_el) = tweet_body.select(&QUOTE_SEL).next() {
         let quoted_fullname = quote_el
@@ -285,41 +265,30 @@ async fn recognize_one(link: &XLink) -> Option<Vec<MessageSegment>> {
                 quote_top.push_str(&format!(" ({})", fullname));
             }
             quote_top.push_str(&format!(":\n{}", content));
-            segments.push(MessageSegment::Text { text: quote_top });
+            message = message.text(quote_top);
 
             let mut quote_last_was_image = false;
             if let Some(media_container) = quote_el.select(&QUOTE_MEDIA_SEL).next() {
                 for img_url in extract_images(&media_container, &ATTACHMENT_IMG_SEL) {
-                    segments.push(MessageSegment::Image {
-                        file: img_url,
-                        r#type: None,
-                        url: None,
-                        cache: Some(true),
-                        proxy: Some(false),
-                        timeout: Some(10),
-                    });
+                    message = message.image_url(img_url).proxy(false).timeout(10);
                     quote_last_was_image = true;
                 }
 
                 if media_container.select(&GALLERY_VIDEO_SEL).next().is_some() {
                     let br = if quote_last_was_image { "" } else { "\n" };
-                    segments.push(MessageSegment::Text {
-                        text: format!("{}[引用推文中含有视频，当前不支持解析]", br),
-                    });
+                    message = message.text(format!("{}[引用推文中含有视频，当前不支持解析]", br));
                     quote_last_was_image = false;
                 }
             }
 
             if let Some(ref date) = quoted_date {
                 let br = if quote_last_was_image { "" } else { "\n" };
-                segments.push(MessageSegment::Text {
-                    text: format!("{}🕒 {}", br, date),
-                });
+                message = message.text(format!("{}🕒 {}", br, date));
             }
         }
     }
 
-    Some(segments)
+    Some(message)
 }
 
 #[cfg(test)]
@@ -3,10 +3,7 @@ use std::{sync::LazyLock, time::Duration};
 use bocchi::schema::MessageSegment;
 use serde::Deserialize;
 
-use crate::{
-    plugin::url_detail::{RecognizedContent, RecognizedMessage},
-    utils::HTTP_CLIENT,
-};
+use crate::{plugin::url_detail::RecognizedContent, utils::HTTP_CLIENT};
 
 static YOUTUBE_VIDEO_REGEX: LazyLock<Vec<regex::Regex>> = LazyLock::new(|| {
     vec![
@@ -61,7 +58,7 @@ fn parse_video_id(text: &str) -> Option<&str> {
         .iter()
         .find_map(|re| re.captures(text).and_then(|cap| cap.get(1).map(|f| f.as_str())))
 }
-pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
+pub(crate) async fn recognizer(text: &str) -> Option<RecognizedContent> {
     let video_id = parse_video_id(text)?;
     let url = format!(
         "https://www.googleapis.com/youtube/v3/videos?part=snippet&id={}&key={}",
@@ -96,10 +93,7 @@ pub(crate) async fn recognizer(text: &str) -> Option<RecognizedMessage> {
             video_detail.title, video_detail.channel_title, video_detail.published_at
         ),
     });
-    Some(RecognizedMessage::new(
-        RecognizedContent::Normal(message_segment),
-        Vec::new(),
-    ))
+    Some(RecognizedContent::Normal(message_segment.into()))
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 use std::{path::PathBuf, sync::LazyLock};
 
-use bocchi::{chain::Rule, plugin::Plugin, schema::MessageSegment};
+use bocchi::{chain::Rule, plugin::Plugin, schema::MessageBuilder};
 use futures::StreamExt;
 use rand::seq::IteratorRandom;
 use tokio::fs;
@@ -49,22 +49,10 @@ pub fn what_to_eat_plugin() -> Plugin {
             }
             .await;
             let msg = match res {
-                Err(_) => vec![MessageSegment::Text {
-                    text: "出错啦，请稍后再试".to_string(),
-                }],
-                Ok((food_name, image_content)) => vec![
-                    MessageSegment::Text {
-                        text: format!("今天吃{food_name}！"),
-                    },
-                    MessageSegment::Image {
-                        file: format!("base64://{}", base64_simd::STANDARD.encode_to_string(image_content)),
-                        r#type: None,
-                        url: None,
-                        cache: Some(true),
-                        proxy: None,
-                        timeout: None,
-                    },
-                ],
+                Err(_) => MessageBuilder::new().text("出错啦，请稍后再试"),
+                Ok((food_name, image_content)) => MessageBuilder::new()
+                    .text(format!("今天吃{food_name}！"))
+                    .image_bytes(image_content),
             };
             ctx.reply_content(msg).await?;
             Ok(true)